@@ -0,0 +1,74 @@
+use glb_to_webgpu::wgpu;
+use log::{info, warn};
+
+use crate::options::GpuOptions;
+
+/// Creates an instance for the chosen backends
+pub fn instance(options: &GpuOptions) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: options.backends,
+        ..Default::default()
+    })
+}
+
+fn describe(adapter: &wgpu::Adapter) -> String {
+    let info = adapter.get_info();
+    format!(
+        "{} ({:?}, {:?}, driver {} {})",
+        info.name, info.backend, info.device_type, info.driver, info.driver_info
+    )
+}
+
+/// Prints every adapter on the chosen backends, for picking one by name
+pub fn list(options: &GpuOptions) {
+    let adapters = instance(options).enumerate_adapters(options.backends);
+    if adapters.is_empty() {
+        println!("No adapters found");
+    }
+    for (i, adapter) in adapters.iter().enumerate() {
+        println!("{}: {}", i, describe(adapter));
+    }
+}
+
+/// Finds an adapter which can render to the surface: the one named in the
+/// options if it can, or else the best match for their power preference
+pub async fn select(
+    instance: &wgpu::Instance,
+    surface: &wgpu::Surface<'_>,
+    options: &GpuOptions,
+) -> wgpu::Adapter {
+    let named = options.adapter.as_ref().and_then(|name| {
+        let name = name.to_lowercase();
+        let adapters = instance.enumerate_adapters(options.backends);
+        let names = adapters
+            .iter()
+            .map(|a| a.get_info().name)
+            .collect::<Vec<_>>();
+        let adapter = adapters
+            .into_iter()
+            .filter(|a| a.is_surface_supported(surface))
+            .find(|a| a.get_info().name.to_lowercase().contains(&name));
+        if adapter.is_none() {
+            warn!(
+                "No adapter named '{}' can draw to the window (found {}), so using the default",
+                name,
+                names.join(", ")
+            );
+        }
+        adapter
+    });
+    let adapter = match named {
+        Some(adapter) => adapter,
+        None => instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: options.power_preference,
+                force_fallback_adapter: options.fallback_adapter,
+                // Request an adapter which can render to our surface
+                compatible_surface: Some(surface),
+            })
+            .await
+            .expect("Failed to find an appropriate adapter"),
+    };
+    info!("Using adapter {}", describe(&adapter));
+    adapter
+}
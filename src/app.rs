@@ -1,53 +1,369 @@
-use gltf;
-use crate::winit::{
+use glb_to_webgpu::gltf;
+use log::{error, info, warn};
+use glb_to_webgpu::winit::{
     dpi::PhysicalSize,
-    event::{DeviceEvent, MouseScrollDelta},
+    event::{DeviceEvent, ElementState, KeyEvent, MouseButton, MouseScrollDelta, TouchPhase},
 };
+use nalgebra_glm::{Vec2, Vec3};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::{backdrop::Backdrop, camera::Camera, model::Model};
+use glb_to_webgpu::{wgpu, Bookmarks, Camera, CameraMode, Instant, PresetView, Renderer, Target};
 
-pub struct App<'a> {
-    surface: wgpu::Surface<'a>,
+use crate::{
+    bindings::{Action, Bindings, BOOST_KEY, FLY_KEYS},
+    gesture::Touches,
+    options::Options,
+    screenshot,
+};
+
+pub struct App {
+    /// The window's surface, or `None` while the app is suspended
+    surface: Option<wgpu::Surface<'static>>,
     adapter: wgpu::Adapter,
     device: wgpu::Device,
-    swapchain_format: wgpu::TextureFormat,
-    model: Model,
-    backdrop: Backdrop,
+    queue: wgpu::Queue,
+    /// Set by the device's lost callback, so the next frame recovers
+    device_lost: Arc<AtomicBool>,
+    /// When to try recreating a lost device again, after an attempt failed
+    retry_device: Option<Instant>,
+    present_mode: wgpu::PresentMode,
+    renderer: Renderer,
     pub camera: Camera,
-    depth: (wgpu::Texture, wgpu::TextureView),
     size: PhysicalSize<u32>,
+
+    /// The glTF document, used to describe picked primitives
+    document: gltf::Document,
+    /// Latest cursor position, in pixels
+    cursor: Vec2,
+    /// Cursor position when the left button was pressed, used to tell
+    /// clicks apart from drags
+    click_start: Option<Vec2>,
+    touches: Touches,
+
+    bindings: Bindings,
+    /// Index of the selected glTF animation
+    animation: Option<usize>,
+    /// Index of the glTF camera which was last jumped to
+    scene_camera: Option<usize>,
+    bookmarks: Bookmarks,
+    /// Set when a screenshot should be saved after the next frame
+    screenshot: bool,
+
+    /// Names of the keys which are currently held down
+    held: HashSet<String>,
+    /// Time at which the last frame was drawn, for frame-rate independent
+    /// movement
+    last_frame: Instant,
 }
 
-impl<'a> App<'a> {
+/// Longest time step applied to movement, so that a stall doesn't turn into
+/// a leap
+const MAX_TIME_STEP: f32 = 1.0 / 20.0;
+
+/// Maximum cursor travel, in pixels, for a press and release to be a click
+const CLICK_DISTANCE: f32 = 3.0;
+
+/// How long to wait before trying again to recreate a lost device
+const DEVICE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+impl App {
+    /// Creates the app for a window's surface.  Fails if the scene can't be
+    /// set up, e.g. because an image named by the options can't be read.
     pub fn new(
         size: PhysicalSize<u32>,
         adapter: wgpu::Adapter,
-        surface: wgpu::Surface<'a>,
+        surface: wgpu::Surface<'static>,
         device: wgpu::Device,
+        queue: wgpu::Queue,
         gltf: gltf::Gltf,
-    ) -> Self {
-        let swapchain_format = surface.get_capabilities(&adapter).formats[0];
-
-        let depth = Self::rebuild_depth_(size, &device, swapchain_format);
-        let backdrop = Backdrop::new(&device, swapchain_format);
-
-        let (model, vertices) = Model::new(&device, swapchain_format, &gltf);
+        options: &Options,
+    ) -> Result<Self, String> {
+        let capabilities = surface.get_capabilities(&adapter);
+        let swapchain_format = capabilities.formats[0];
+        // The automatic modes fall back on whatever the surface supports
+        let mut present_mode = options.gpu.present_mode;
+        let automatic = [wgpu::PresentMode::AutoVsync, wgpu::PresentMode::AutoNoVsync];
+        if !automatic.contains(&present_mode)
+            && !capabilities.present_modes.contains(&present_mode)
+        {
+            warn!(
+                "Surface only supports {:?} presentation, not {:?}, so using vsync",
+                capabilities.present_modes, present_mode
+            );
+            present_mode = wgpu::PresentMode::AutoVsync;
+        }
+        let renderer = Renderer::new(
+            &device,
+            &queue,
+            &gltf,
+            swapchain_format,
+            size,
+            &options.render,
+        )?;
         let mut camera = Camera::new(size.width as f32, size.height as f32);
-        camera.fit_verts(&vertices);
+        camera.set_inertia(options.inertia);
+        camera.fit_now(renderer.bounds());
 
-        Self {
-            depth,
-            backdrop,
-            swapchain_format,
-            model,
+        let bookmarks = Bookmarks::load(options.model.as_deref());
+        if let Some(name) = &options.bookmark {
+            match bookmarks.find(name) {
+                Some(bookmark) => camera.go_to_bookmark(bookmark, false),
+                None if bookmarks.list().is_empty() => {
+                    warn!("No bookmark named '{}', as there are no bookmarks", name);
+                }
+                None => {
+                    let names = bookmarks.list().iter().map(|b| b.name.as_str());
+                    warn!(
+                        "No bookmark named '{}', expected one of {}",
+                        name,
+                        names.collect::<Vec<_>>().join(", ")
+                    );
+                }
+            }
+        }
+
+        let out = Self {
+            device_lost: Self::watch_device(&device),
+            retry_device: None,
+            present_mode,
+            queue,
+            renderer,
             camera,
-            surface,
+            surface: Some(surface),
             device,
             adapter,
             size,
+            document: gltf.document,
+            cursor: Vec2::zeros(),
+            click_start: None,
+            touches: Touches::default(),
+            bindings: options.bindings.clone(),
+            animation: None,
+            scene_camera: None,
+            bookmarks,
+            screenshot: false,
+            held: HashSet::new(),
+            last_frame: Instant::now(),
+        };
+        out.configure_surface();
+        info!("Key bindings: {}", out.bindings.describe());
+        Ok(out)
+    }
+
+    pub fn key_input(&mut self, event: &KeyEvent) {
+        if let Some(name) = Bindings::key_name(&event.logical_key) {
+            match event.state {
+                ElementState::Pressed => self.held.insert(name),
+                ElementState::Released => self.held.remove(&name),
+            };
+        }
+        if event.state != ElementState::Pressed || event.repeat {
+            return;
+        }
+        if let Some(action) = self.bindings.action(&event.logical_key) {
+            self.run_action(action);
+        }
+    }
+
+    /// Forgets held keys, e.g. when the window loses focus and won't see
+    /// them being released
+    pub fn release_keys(&mut self) {
+        self.held.clear();
+    }
+
+    /// Returns the view-space direction of travel from the held fly keys
+    fn fly_direction(&self) -> Vec3 {
+        FLY_KEYS
+            .iter()
+            .filter(|(k, _)| self.held.contains(*k))
+            .map(|(_, d)| Vec3::from(*d))
+            .sum()
+    }
+
+    /// Returns true if the camera is moving, so frames should keep coming
+    pub fn is_animating(&self) -> bool {
+        self.camera.is_animating()
+            || (self.camera.mode() == CameraMode::Fly && self.fly_direction() != Vec3::zeros())
+    }
+
+    pub fn run_action(&mut self, action: Action) {
+        match action {
+            Action::ResetView => self.camera.reset(self.renderer.bounds()),
+            Action::Fit => self.camera.fit(self.renderer.bounds()),
+            Action::ToggleFly => {
+                self.camera.toggle_fly();
+                info!("Camera: {:?}", self.camera.mode());
+            }
+            Action::ToggleProjection => {
+                self.camera.toggle_projection();
+                info!("Projection: {:?}", self.camera.projection());
+            }
+            Action::CycleViewMode => self.cycle_view_mode(),
+            Action::ToggleShadows => {
+                let shadows = !self.renderer.shadows();
+                info!("Shadows {}", if shadows { "on" } else { "off" });
+                self.renderer.set_shadows(shadows);
+            }
+            Action::ToggleEffect(kind) => {
+                let enabled = !self.renderer.effect(kind);
+                info!("{:?} {}", kind, if enabled { "on" } else { "off" });
+                self.renderer.set_effect(kind, enabled);
+            }
+            Action::NextAnimation => self.step_animation(1),
+            Action::PrevAnimation => self.step_animation(-1),
+            Action::Screenshot => self.screenshot = true,
+            Action::ViewFront => self.camera.set_preset(PresetView::Front),
+            Action::ViewTop => self.camera.set_preset(PresetView::Top),
+            Action::ViewSide => self.camera.set_preset(PresetView::Side),
+            Action::NextCamera => self.next_scene_camera(),
+            Action::SaveBookmark => {
+                let bookmark = self.camera.bookmark(self.bookmarks.next_name());
+                info!("Saved bookmark {}", bookmark.name);
+                self.bookmarks.add(bookmark);
+            }
+            Action::NextBookmark => self.step_bookmark(1),
+            Action::PrevBookmark => self.step_bookmark(-1),
+            Action::ListBookmarks => {
+                info!("{} bookmarks", self.bookmarks.list().len());
+                for (i, b) in self.bookmarks.list().iter().enumerate() {
+                    info!("  {}: {}", i + 1, b.name);
+                }
+            }
+        }
+    }
+
+    fn step_bookmark(&mut self, delta: isize) {
+        match self.bookmarks.step(delta) {
+            Some(bookmark) => {
+                info!("Bookmark: {}", bookmark.name);
+                self.camera.go_to_bookmark(bookmark, true);
+            }
+            None => info!("No bookmarks saved for this model"),
+        }
+    }
+
+    /// Moves to the next camera defined in the glTF scene
+    fn next_scene_camera(&mut self) {
+        let cameras = self.renderer.model().cameras();
+        if cameras.is_empty() {
+            info!("Model has no cameras");
+            return;
+        }
+        let i = self.scene_camera.map(|i| (i + 1) % cameras.len()).unwrap_or(0);
+        self.scene_camera = Some(i);
+        info!("Camera {}/{}: {}", i + 1, cameras.len(), cameras[i].name);
+        self.camera.go_to(&cameras[i], self.renderer.bounds());
+    }
+
+    /// Selects the next or previous animation in the file.  Animations
+    /// aren't played back yet, so this only reports which one is selected.
+    fn step_animation(&mut self, delta: isize) {
+        let count = self.document.animations().len();
+        if count == 0 {
+            info!("Model has no animations");
+            return;
+        }
+        let i = match self.animation {
+            Some(i) => (i as isize + delta).rem_euclid(count as isize) as usize,
+            None if delta < 0 => count - 1,
+            None => 0,
+        };
+        self.animation = Some(i);
+        let animation = self.document.animations().nth(i).unwrap();
+        info!(
+            "Animation {}/{}: {}",
+            i + 1,
+            count,
+            animation.name().unwrap_or("(unnamed)")
+        );
+    }
+
+    pub fn mouse_input(&mut self, button: MouseButton, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                if button == MouseButton::Left {
+                    self.click_start = Some(self.cursor);
+                }
+                self.camera.mouse_pressed(button)
+            }
+            ElementState::Released => {
+                if button == MouseButton::Left {
+                    let start = self.click_start.take();
+                    if start.is_some_and(|s| (s - self.cursor).norm() < CLICK_DISTANCE) {
+                        self.pick(self.cursor);
+                    }
+                }
+                self.camera.mouse_released(button)
+            }
         }
     }
 
+    pub fn cursor_moved(&mut self, pos: Vec2) {
+        self.cursor = pos;
+        self.camera.mouse_move(pos);
+    }
+
+    pub fn touch(&mut self, id: u64, phase: TouchPhase, pos: Vec2) {
+        if let Some(tap) = self.touches.update(&mut self.camera, id, phase, pos) {
+            self.pick(tap);
+        }
+    }
+
+    /// Handles a trackpad pinch, where `delta` is the change in magnification
+    pub fn pinch(&mut self, delta: f32) {
+        self.camera.touch_zoom(1.0 + delta, self.cursor);
+    }
+
+    /// Handles a trackpad rotation, given in counterclockwise degrees
+    pub fn rotation_gesture(&mut self, delta: f32) {
+        self.camera.twist(-delta.to_radians());
+    }
+
+    /// Selects the primitive under a position in pixels and logs what it is,
+    /// or clears the selection if there's nothing there
+    fn pick(&mut self, pos: Vec2) {
+        let (origin, dir) = self.camera.ray(pos);
+        let Some(pick) = self.renderer.model().pick(origin, dir) else {
+            info!("Picked nothing");
+            self.renderer.model_mut().set_selected(None);
+            return;
+        };
+        self.renderer.model_mut().set_selected(Some(pick.primitive));
+
+        let prim = &self.renderer.model().primitives()[pick.primitive];
+        let node = prim
+            .node
+            .and_then(|i| self.document.nodes().nth(i))
+            .map(|n| n.name().map(str::to_owned).unwrap_or(format!("#{}", n.index())))
+            .unwrap_or_else(|| "(none)".to_owned());
+        let mesh = self.document.meshes().nth(prim.mesh).unwrap();
+        let primitive = mesh.primitives().nth(prim.index).unwrap();
+        let material = primitive.material();
+        let attributes = primitive
+            .attributes()
+            .map(|(semantic, _)| semantic.to_string())
+            .collect::<Vec<_>>();
+        info!(
+            "Picked node {}, mesh {} primitive {}, triangle {} at {:?}",
+            node,
+            mesh.name().map(str::to_owned).unwrap_or(format!("#{}", mesh.index())),
+            prim.index,
+            pick.triangle,
+            pick.position.as_slice(),
+        );
+        info!(
+            "  material: {}",
+            match material.index() {
+                Some(i) => material.name().map(str::to_owned).unwrap_or(format!("#{}", i)),
+                None => "(default)".to_owned(),
+            }
+        );
+        info!("  attributes: {}", attributes.join(", "));
+    }
+
     pub fn device_event(&mut self, e: DeviceEvent) {
         if let DeviceEvent::MouseWheel { delta } = e {
             if let MouseScrollDelta::PixelDelta(p) = delta {
@@ -56,61 +372,194 @@ impl<'a> App<'a> {
         }
     }
 
+    /// Switches to the next debug view mode
+    pub fn cycle_view_mode(&mut self) {
+        let mode = self.renderer.model().view_mode().next();
+        info!("View mode: {:?}", mode);
+        self.renderer.model_mut().set_view_mode(mode);
+    }
+
+    /// Configures the surface for the current size.  Frames are copied back
+    /// for screenshots, if the surface allows it.
+    fn configure_surface(&self) {
+        let Some(surface) = &self.surface else {
+            return;
+        };
+        if self.is_minimized() {
+            return;
+        }
+        let mut config = surface
+            .get_default_config(&self.adapter, self.size.width, self.size.height)
+            .unwrap();
+        let usages = surface.get_capabilities(&self.adapter).usages;
+        if usages.contains(wgpu::TextureUsages::COPY_SRC) {
+            config.usage |= wgpu::TextureUsages::COPY_SRC;
+        }
+        config.present_mode = self.present_mode;
+        surface.configure(&self.device, &config);
+    }
+
+    /// Drops the surface, which mustn't outlive its window's native handle
+    /// while the app is suspended
+    pub fn suspend(&mut self) {
+        self.surface = None;
+    }
+
+    /// Starts drawing again after a suspend, into a new surface for the
+    /// window at its current size
+    pub fn resume(&mut self, surface: wgpu::Surface<'static>, size: PhysicalSize<u32>) {
+        self.surface = Some(surface);
+        self.resize(size);
+    }
+
+    /// Returns true while suspended, when there's nothing to draw into
+    pub fn is_suspended(&self) -> bool {
+        self.surface.is_none()
+    }
+
+    /// Returns true if the window has no area (e.g. it's minimized), so
+    /// there's nothing to draw
+    pub fn is_minimized(&self) -> bool {
+        self.size.width == 0 || self.size.height == 0
+    }
+
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
         self.size = size;
-        self.surface.configure(
-            &self.device,
-            &self
-                .surface
-                .get_default_config(&self.adapter, size.width, size.height)
-                .unwrap(),
-        );
+        if self.is_minimized() {
+            return;
+        }
+        self.configure_surface();
 
         self.camera.set_size(size.width as f32, size.height as f32);
-        self.depth = Self::rebuild_depth_(size, &self.device, self.swapchain_format);
+        self.renderer.resize(&self.device, size);
     }
 
-    pub fn rebuild_depth_(
-        size: PhysicalSize<u32>,
-        device: &wgpu::Device,
-        view_format: wgpu::TextureFormat,
-    ) -> (wgpu::Texture, wgpu::TextureView) {
-        let size = wgpu::Extent3d {
-            width: size.width,
-            height: size.height,
-            depth_or_array_layers: 1,
+    /// Acquires the next frame from the surface.  A lost or outdated surface
+    /// is reconfigured and retried once, and a frame which still can't be had
+    /// (e.g. on a timeout) is skipped by returning `None`.  Only running out
+    /// of memory is an error.
+    fn next_frame(&self) -> Result<Option<wgpu::SurfaceTexture>, wgpu::SurfaceError> {
+        let Some(surface) = &self.surface else {
+            return Ok(None);
         };
-        let desc = wgpu::TextureDescriptor {
-            label: Some("depth tex"),
-            size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            // format: view_format,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT, // | wgpu::TextureUsages::SAMPLED,
-            // view_formats: &[view_format],
-            view_formats: &[wgpu::TextureFormat::Depth32Float],
+        let mut retried = false;
+        loop {
+            match surface.get_current_texture() {
+                Ok(frame) => return Ok(Some(frame)),
+                Err(e @ (wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated))
+                    if !retried =>
+                {
+                    info!("Reconfiguring surface: {}", e);
+                    self.configure_surface();
+                    retried = true;
+                }
+                Err(wgpu::SurfaceError::OutOfMemory) => {
+                    return Err(wgpu::SurfaceError::OutOfMemory);
+                }
+                Err(e) => {
+                    warn!("Skipping frame: {}", e);
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Returns a flag which is set if the device is lost
+    fn watch_device(device: &wgpu::Device) -> Arc<AtomicBool> {
+        let lost = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&lost);
+        device.set_device_lost_callback(move |reason, message| {
+            // Dropping the old device after replacing it isn't a loss
+            if !matches!(reason, wgpu::DeviceLostReason::Dropped) {
+                warn!("Device lost ({:?}): {}", reason, message);
+                flag.store(true, Ordering::SeqCst);
+            }
+        });
+        lost
+    }
+
+    /// Replaces a lost device with a new one, rebuilding the surface
+    /// configuration and every GPU resource from the CPU-side scene
+    fn recover_device(&mut self) -> Result<(), wgpu::RequestDeviceError> {
+        info!("Recreating the device");
+        let (device, queue) = pollster::block_on(
+            self.adapter.request_device(&Default::default(), None),
+        )?;
+        self.device_lost = Self::watch_device(&device);
+        self.device = device;
+        self.queue = queue;
+        self.configure_surface();
+        self.renderer.rebuild(&self.device, &self.queue);
+        Ok(())
+    }
+
+    /// Returns when to try again to recreate the lost device, if an attempt
+    /// failed
+    pub fn device_retry(&self) -> Option<Instant> {
+        self.retry_device
+    }
+
+    /// Draws a frame into the window, unless it's minimized or suspended, or
+    /// the frame has to be skipped
+    pub fn redraw(&mut self) -> Result<(), wgpu::SurfaceError> {
+        if self.device_lost.load(Ordering::SeqCst) {
+            if self.retry_device.is_some_and(|t| t > Instant::now()) {
+                return Ok(());
+            }
+            // The flag stays set on failure, so a later frame tries again
+            if let Err(e) = self.recover_device() {
+                error!("Failed to recreate the device: {}", e);
+                self.retry_device = Some(Instant::now() + DEVICE_RETRY_DELAY);
+                return Ok(());
+            }
+            self.retry_device = None;
+        }
+        if self.is_minimized() {
+            return Ok(());
+        }
+        let Some(frame) = self.next_frame()? else {
+            return Ok(());
         };
-        let tex = device.create_texture(&desc);
-        let view = tex.create_view(&wgpu::TextureViewDescriptor::default());
-        (tex, view)
+        let view = frame.texture.create_view(&Default::default());
+        self.draw_to(&Target::new(&frame.texture, &view));
+
+        if std::mem::take(&mut self.screenshot) {
+            if frame.texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+                let secs = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                let path = format!("screenshot-{}.png", secs);
+                screenshot::save(&self.device, &self.queue, &frame.texture, path.as_ref());
+            } else {
+                warn!("This surface doesn't support screenshots");
+            }
+        }
+        let suboptimal = frame.suboptimal;
+        frame.present();
+        if suboptimal {
+            self.configure_surface();
+        }
+        Ok(())
     }
 
-    pub fn redraw(&mut self, queue: &wgpu::Queue) {
-        let frame = self
-            .surface
-            .get_current_texture()
-            .expect("Failed to acquire next swap chain texture");
+    /// Moves the camera on and draws a frame into any target, such as an
+    /// offscreen texture, rather than the window
+    pub fn draw_to(&mut self, target: &Target) {
+        let now = Instant::now();
+        let dt = (now - self.last_frame).as_secs_f32().min(MAX_TIME_STEP);
+        self.last_frame = now;
+        let boost = self.held.contains(BOOST_KEY);
+        self.camera.fly(self.fly_direction(), boost, dt);
+        self.camera.update(dt);
+        self.camera
+            .set_size(target.size.width as f32, target.size.height as f32);
 
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-
-        self.backdrop.draw(&frame, &self.depth.1, &mut encoder);
-        self.model
-            .draw(&self.camera, &queue, &frame, &self.depth.1, &mut encoder);
-        queue.submit(Some(encoder.finish()));
-        frame.present();
+        self.renderer
+            .draw(&self.device, &self.queue, &self.camera, target, &mut encoder);
+        self.queue.submit(Some(encoder.finish()));
     }
 }
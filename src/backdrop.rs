@@ -1,11 +1,246 @@
 use std::borrow::Cow;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra_glm as glm;
+
+use crate::{camera::Camera, renderer::DEPTH_FORMAT, texture::Texture};
+
+/// How the area behind the model is filled
+#[derive(Clone, Debug, PartialEq)]
+pub enum BackdropStyle {
+    /// A single flat color
+    Solid([f32; 3]),
+
+    /// A linear blend from `start` to `end`.  With an `angle` of zero the
+    /// gradient runs from the bottom of the window to the top; positive
+    /// angles (in degrees) rotate it clockwise.
+    Gradient {
+        start: [f32; 3],
+        end: [f32; 3],
+        angle: f32,
+    },
+
+    /// An LDR image, scaled to cover the window
+    Image(PathBuf),
+
+    /// An equirectangular HDR environment, drawn as a skybox which follows
+    /// the camera rotation
+    Environment(PathBuf),
+}
+
+impl Default for BackdropStyle {
+    fn default() -> Self {
+        BackdropStyle::Gradient {
+            start: [0.05, 0.06, 0.10],
+            end: [0.17, 0.22, 0.29],
+            angle: 0.0,
+        }
+    }
+}
+
+/// Parses a `#rrggbb` color (the leading `#` is optional) into linear RGB
+fn parse_color(s: &str) -> Result<[f32; 3], String> {
+    let hex = s.trim_start_matches('#');
+    if hex.len() != 6 {
+        return Err(format!("Invalid color '{}', expected #rrggbb", s));
+    }
+    let mut out = [0.0; 3];
+    for (i, c) in out.iter_mut().enumerate() {
+        let v = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| format!("Invalid color '{}', expected #rrggbb", s))?;
+        let v = v as f32 / 255.0;
+        // Colors are written in sRGB, but shading happens in linear space
+        *c = if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        };
+    }
+    Ok(out)
+}
+
+impl FromStr for BackdropStyle {
+    type Err = String;
+
+    /// Parses one of
+    /// - `solid:#rrggbb`
+    /// - `gradient:#rrggbb:#rrggbb[:angle]`
+    /// - `image:<path>`
+    /// - `env:<path>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, rest) = s.split_once(':').unwrap_or((s, ""));
+        match kind {
+            "solid" => Ok(BackdropStyle::Solid(parse_color(rest)?)),
+            "gradient" => {
+                let parts = rest.split(':').collect::<Vec<_>>();
+                match parts.as_slice() {
+                    [start, end] | [start, end, _] => Ok(BackdropStyle::Gradient {
+                        start: parse_color(start)?,
+                        end: parse_color(end)?,
+                        angle: match parts.get(2) {
+                            Some(a) => a
+                                .parse()
+                                .map_err(|_| format!("Invalid gradient angle '{}'", a))?,
+                            None => 0.0,
+                        },
+                    }),
+                    _ => Err(format!(
+                        "Invalid gradient '{}', expected gradient:#rrggbb:#rrggbb[:angle]",
+                        s
+                    )),
+                }
+            }
+            "image" if !rest.is_empty() => Ok(BackdropStyle::Image(rest.into())),
+            "env" if !rest.is_empty() => Ok(BackdropStyle::Environment(rest.into())),
+            _ => Err(format!(
+                "Invalid backdrop '{}', expected solid:, gradient:, image: or env:",
+                s
+            )),
+        }
+    }
+}
+
+/// Uniforms for `backdrop.wgsl`, which must match `Locals` in the shader
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Locals {
+    /// Rotates view-space rays into world space (environment mode only)
+    rotation: [[f32; 4]; 4],
+    color_a: [f32; 4],
+    color_b: [f32; 4],
+    mode: u32,
+    /// Gradient angle, in radians
+    angle: f32,
+    /// Window aspect ratio (width / height)
+    aspect: f32,
+    /// Image aspect ratio (width / height)
+    image_aspect: f32,
+}
+
+const MODE_SOLID: u32 = 0;
+const MODE_GRADIENT: u32 = 1;
+const MODE_IMAGE: u32 = 2;
+const MODE_ENVIRONMENT: u32 = 3;
 
 pub struct Backdrop {
     render_pipeline: wgpu::RenderPipeline,
+    uniform_buf: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    locals: Locals,
 }
 
 impl Backdrop {
-    pub fn new(device: &wgpu::Device, swapchain_format: wgpu::TextureFormat) -> Self {
+    /// Creates the pipeline for a style.  Image and environment styles draw
+    /// `texture`, which should hold the file named by the style.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        style: &BackdropStyle,
+        texture: Option<&Texture>,
+    ) -> Self {
+        let mut locals = Locals {
+            rotation: glm::Mat4::identity().into(),
+            color_a: [0.0, 0.0, 0.0, 1.0],
+            color_b: [0.0, 0.0, 0.0, 1.0],
+            mode: MODE_SOLID,
+            angle: 0.0,
+            aspect: 1.0,
+            image_aspect: 1.0,
+        };
+        match style {
+            BackdropStyle::Solid(c) => {
+                locals.color_a = [c[0], c[1], c[2], 1.0];
+            }
+            BackdropStyle::Gradient { start, end, angle } => {
+                locals.mode = MODE_GRADIENT;
+                locals.color_a = [start[0], start[1], start[2], 1.0];
+                locals.color_b = [end[0], end[1], end[2], 1.0];
+                locals.angle = angle.to_radians();
+            }
+            BackdropStyle::Image(_) => locals.mode = MODE_IMAGE,
+            BackdropStyle::Environment(_) => locals.mode = MODE_ENVIRONMENT,
+        }
+        let placeholder;
+        let texture = match texture {
+            Some(texture) => texture,
+            None => {
+                placeholder = Texture::solid(device, queue, [0, 0, 0, 255]);
+                &placeholder
+            }
+        };
+        locals.image_aspect = texture.width as f32 / texture.height as f32;
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Backdrop Uniform Buffer"),
+            size: std::mem::size_of::<Locals>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<Locals>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: None,
+        });
+
         let backdrop_src = Cow::Borrowed(include_str!("backdrop.wgsl"));
 
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -15,7 +250,7 @@ impl Backdrop {
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[],
+            bind_group_layouts: &[&bind_group_layout],
             push_constant_ranges: &[],
         });
 
@@ -31,12 +266,12 @@ impl Backdrop {
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: "fs_main".into(),
-                targets: &[Some(swapchain_format.into())],
+                targets: &[Some(format.into())],
                 // compilation_options: Default::default(),
             }),
             primitive: wgpu::PrimitiveState::default(),
             depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
+                format: DEPTH_FORMAT,
                 depth_write_enabled: true,
                 depth_compare: wgpu::CompareFunction::Always,
                 stencil: wgpu::StencilState::default(),
@@ -47,37 +282,53 @@ impl Backdrop {
             // cache: None,
         });
 
-        Backdrop { render_pipeline }
+        Backdrop {
+            render_pipeline,
+            uniform_buf,
+            bind_group,
+            locals,
+        }
     }
 
     pub fn draw(
-        &self,
-        frame: &wgpu::SurfaceTexture,
+        &mut self,
+        camera: &Camera,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
         depth_view: &wgpu::TextureView,
         encoder: &mut wgpu::CommandEncoder,
     ) {
-        let view = frame.texture.create_view(&Default::default());
+        // The skybox looks along view-space rays, so undo the camera rotation
+        // to find the matching world-space direction
+        self.locals.rotation = camera.rotation_matrix().transpose().into();
+        self.locals.aspect = camera.aspect();
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&self.locals));
+
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: None,
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                     store: wgpu::StoreOp::Store,
                 },
             })],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &depth_view,
+                view: depth_view,
                 depth_ops: Some(wgpu::Operations {
                     load: wgpu::LoadOp::Clear(0.0),
                     store: wgpu::StoreOp::Store,
                 }),
-                stencil_ops: None,
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: wgpu::StoreOp::Store,
+                }),
             }),
             ..Default::default()
         });
         rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
         rpass.draw(0..6, 0..1);
     }
 }
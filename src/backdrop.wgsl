@@ -1,33 +1,86 @@
 struct VertexOutput {
-    @location(0) color: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
     @builtin(position) position: vec4<f32>,
 };
 
+struct Locals {
+    rotation: mat4x4<f32>,
+    color_a: vec4<f32>,
+    color_b: vec4<f32>,
+    mode: u32,
+    angle: f32,
+    aspect: f32,
+    image_aspect: f32,
+};
+@group(0) @binding(0)
+var<uniform> r_locals: Locals;
+@group(0) @binding(1)
+var r_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var r_sampler: sampler;
+
+const MODE_SOLID: u32 = 0u;
+const MODE_GRADIENT: u32 = 1u;
+const MODE_IMAGE: u32 = 2u;
+const MODE_ENVIRONMENT: u32 = 3u;
+
+const PI: f32 = 3.14159265359;
+
+// Half-width of the skybox field of view, as tan(fov / 2)
+const SKYBOX_TAN_HALF_FOV: f32 = 0.7;
+
 @vertex
 fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
     var out: VertexOutput;
-    var c1: vec4<f32> = vec4<f32>(0.05, 0.06, 0.10, 1.0);
-    var c2: vec4<f32> = vec4<f32>(0.17, 0.22, 0.29, 1.0);
+    var pos: vec2<f32>;
     if (in_vertex_index == 0u || in_vertex_index == 5u) {
-        out.color = c1;
-        out.position = vec4<f32>(-1.0, -1.0, 0.0, 1.0);
+        pos = vec2<f32>(-1.0, -1.0);
     } else if (in_vertex_index == 1u) {
-        out.color = c1;
-        out.position = vec4<f32>(1.0, -1.0, 0.0, 1.0);
+        pos = vec2<f32>(1.0, -1.0);
     } else if (in_vertex_index == 2u || in_vertex_index == 3u) {
-        out.color = c2;
-        out.position = vec4<f32>(1.0, 1.0, 0.0, 1.0);
-    } else if (in_vertex_index == 4u) {
-        out.color = c2;
-        out.position = vec4<f32>(-1.0, 1.0, 0.0, 1.0);
+        pos = vec2<f32>(1.0, 1.0);
     } else {
-        out.color = vec4<f32>(0.0, 0.0, 0.0, 1.0);
-        out.position = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        pos = vec2<f32>(-1.0, 1.0);
     }
+    out.ndc = pos;
+    out.position = vec4<f32>(pos, 0.0, 1.0);
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+    switch r_locals.mode {
+        case MODE_GRADIENT: {
+            let dir = vec2<f32>(sin(r_locals.angle), cos(r_locals.angle));
+            let t = clamp(dot(in.ndc, dir) * 0.5 + 0.5, 0.0, 1.0);
+            return mix(r_locals.color_a, r_locals.color_b, t);
+        }
+        case MODE_IMAGE: {
+            // Scale the image to cover the window while keeping its aspect
+            var scale = vec2<f32>(1.0, 1.0);
+            if (r_locals.aspect > r_locals.image_aspect) {
+                scale.y = r_locals.image_aspect / r_locals.aspect;
+            } else {
+                scale.x = r_locals.aspect / r_locals.image_aspect;
+            }
+            let uv = (in.ndc * scale * vec2<f32>(0.5, -0.5)) + 0.5;
+            return vec4<f32>(textureSampleLevel(r_texture, r_sampler, uv, 0.0).rgb, 1.0);
+        }
+        case MODE_ENVIRONMENT: {
+            let view_dir = normalize(vec3<f32>(
+                in.ndc.x * SKYBOX_TAN_HALF_FOV,
+                in.ndc.y * SKYBOX_TAN_HALF_FOV / r_locals.aspect,
+                -1.0,
+            ));
+            let dir = (r_locals.rotation * vec4<f32>(view_dir, 0.0)).xyz;
+            let uv = vec2<f32>(
+                atan2(dir.x, -dir.z) / (2.0 * PI) + 0.5,
+                acos(clamp(dir.y, -1.0, 1.0)) / PI,
+            );
+            return vec4<f32>(textureSampleLevel(r_texture, r_sampler, uv, 0.0).rgb, 1.0);
+        }
+        default: {
+            return r_locals.color_a;
+        }
+    }
 }
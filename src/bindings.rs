@@ -0,0 +1,198 @@
+use std::str::FromStr;
+
+use glb_to_webgpu::{winit::keyboard::Key, EffectKind};
+
+/// Something which can be triggered from the keyboard
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Returns to the front view, fitted around the model
+    ResetView,
+    /// Fits the model in the window, keeping the current rotation
+    Fit,
+    ToggleProjection,
+    /// Switches between the orbit and fly cameras
+    ToggleFly,
+    CycleViewMode,
+    /// Turns the key light's shadows on or off
+    ToggleShadows,
+    /// Turns a post-processing effect on or off
+    ToggleEffect(EffectKind),
+    NextAnimation,
+    PrevAnimation,
+    Screenshot,
+    ViewFront,
+    ViewTop,
+    ViewSide,
+    /// Jumps to the next camera defined in the glTF scene
+    NextCamera,
+    /// Saves the current view as a new bookmark
+    SaveBookmark,
+    NextBookmark,
+    PrevBookmark,
+    ListBookmarks,
+}
+
+impl Action {
+    const NAMES: [(&'static str, Action); 20] = [
+        ("reset", Action::ResetView),
+        ("fit", Action::Fit),
+        ("projection", Action::ToggleProjection),
+        ("fly", Action::ToggleFly),
+        ("view-mode", Action::CycleViewMode),
+        ("shadows", Action::ToggleShadows),
+        ("ssao", Action::ToggleEffect(EffectKind::Ssao)),
+        ("bloom", Action::ToggleEffect(EffectKind::Bloom)),
+        ("fxaa", Action::ToggleEffect(EffectKind::Fxaa)),
+        ("next-animation", Action::NextAnimation),
+        ("prev-animation", Action::PrevAnimation),
+        ("screenshot", Action::Screenshot),
+        ("front", Action::ViewFront),
+        ("top", Action::ViewTop),
+        ("side", Action::ViewSide),
+        ("camera", Action::NextCamera),
+        ("bookmark", Action::SaveBookmark),
+        ("next-bookmark", Action::NextBookmark),
+        ("prev-bookmark", Action::PrevBookmark),
+        ("list-bookmarks", Action::ListBookmarks),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES.iter().find(|(_, a)| a == self).unwrap().0
+    }
+
+    /// Returns the name of every action, in the order they're listed
+    pub fn names() -> Vec<&'static str> {
+        Self::NAMES.iter().map(|(name, _)| *name).collect()
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::NAMES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, a)| *a)
+            .ok_or_else(|| {
+                let names = Self::names().join(", ");
+                format!("Invalid action '{}', expected one of {}", s, names)
+            })
+    }
+}
+
+/// Keys which move the camera while held in fly mode, with their view-space
+/// directions
+pub const FLY_KEYS: [(&str, [f32; 3]); 6] = [
+    ("w", [0.0, 0.0, -1.0]),
+    ("s", [0.0, 0.0, 1.0]),
+    ("a", [-1.0, 0.0, 0.0]),
+    ("d", [1.0, 0.0, 0.0]),
+    ("e", [0.0, 1.0, 0.0]),
+    ("q", [0.0, -1.0, 0.0]),
+];
+
+/// Key which speeds up flying while held
+pub const BOOST_KEY: &str = "shift";
+
+/// A single `KEY=ACTION` binding, as given on the command line.  The action
+/// may be `none`, which unbinds the key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Binding {
+    pub key: String,
+    pub action: Option<Action>,
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, action) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid binding '{}', expected KEY=ACTION", s))?;
+        if key.is_empty() {
+            return Err(format!("Invalid binding '{}', missing key", s));
+        }
+        Ok(Binding {
+            key: key.to_lowercase(),
+            action: match action {
+                "none" => None,
+                a => Some(a.parse()?),
+            },
+        })
+    }
+}
+
+/// Maps keys to actions.  Keys are named by the character they produce
+/// (e.g. `r` or `[`), or by their lowercased winit name for keys which don't
+/// produce text (e.g. `home`, `f12` or `arrowleft`).
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    keys: Vec<(String, Action)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let keys = [
+            ("r", Action::ResetView),
+            ("home", Action::ResetView),
+            ("f", Action::Fit),
+            ("p", Action::ToggleProjection),
+            ("g", Action::ToggleFly),
+            ("v", Action::CycleViewMode),
+            ("h", Action::ToggleShadows),
+            ("o", Action::ToggleEffect(EffectKind::Ssao)),
+            ("n", Action::ToggleEffect(EffectKind::Bloom)),
+            ("x", Action::ToggleEffect(EffectKind::Fxaa)),
+            ("]", Action::NextAnimation),
+            ("[", Action::PrevAnimation),
+            ("f12", Action::Screenshot),
+            // Matching Blender's numpad views
+            ("1", Action::ViewFront),
+            ("7", Action::ViewTop),
+            ("3", Action::ViewSide),
+            ("c", Action::NextCamera),
+            ("b", Action::SaveBookmark),
+            (".", Action::NextBookmark),
+            (",", Action::PrevBookmark),
+            ("l", Action::ListBookmarks),
+        ];
+        Bindings {
+            keys: keys.map(|(k, a)| (k.to_owned(), a)).to_vec(),
+        }
+    }
+}
+
+impl Bindings {
+    /// Applies a binding, replacing anything already bound to its key
+    pub fn bind(&mut self, binding: &Binding) {
+        self.keys.retain(|(k, _)| *k != binding.key);
+        if let Some(action) = binding.action {
+            self.keys.push((binding.key.clone(), action));
+        }
+    }
+
+    /// Returns the action bound to a key, if any
+    pub fn action(&self, key: &Key) -> Option<Action> {
+        let name = Self::key_name(key)?;
+        self.keys.iter().find(|(k, _)| *k == name).map(|(_, a)| *a)
+    }
+
+    /// Returns the name by which a key is bound
+    pub fn key_name(key: &Key) -> Option<String> {
+        match key {
+            Key::Character(c) => Some(c.to_lowercase()),
+            Key::Named(named) => Some(format!("{:?}", named).to_lowercase()),
+            _ => None,
+        }
+    }
+
+    /// Returns a one-line summary of the bindings, for the log
+    pub fn describe(&self) -> String {
+        self.keys
+            .iter()
+            .map(|(k, a)| format!("{} {}", k, a.name()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
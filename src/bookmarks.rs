@@ -0,0 +1,116 @@
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::Projection;
+
+/// A saved camera view
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub center: [f32; 3],
+    pub scale: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub projection: Projection,
+}
+
+/// Contents of the sidecar file
+#[derive(Default, Serialize, Deserialize)]
+struct File {
+    bookmarks: Vec<Bookmark>,
+}
+
+/// Camera bookmarks for a model, which are kept in a JSON file next to it
+/// (e.g. `robot.glb` has its bookmarks in `robot.bookmarks.json`)
+pub struct Bookmarks {
+    /// Sidecar file, or `None` if there's no model file to put it next to
+    path: Option<PathBuf>,
+    list: Vec<Bookmark>,
+    /// Index of the bookmark which was last saved or recalled
+    current: Option<usize>,
+}
+
+impl Bookmarks {
+    /// Loads the bookmarks for a model, if it has any
+    pub fn load(model: Option<&Path>) -> Self {
+        let path = model.map(|m| m.with_extension("bookmarks.json"));
+        let list = match &path {
+            Some(path) if path.exists() => {
+                let data = std::fs::read(path)
+                    .unwrap_or_else(|e| panic!("Failed to read bookmarks {:?}: {}", path, e));
+                let file: File = serde_json::from_slice(&data)
+                    .unwrap_or_else(|e| panic!("Failed to parse bookmarks {:?}: {}", path, e));
+                info!("Loaded {} bookmarks from {:?}", file.bookmarks.len(), path);
+                file.bookmarks
+            }
+            _ => vec![],
+        };
+        Bookmarks {
+            path,
+            list,
+            current: None,
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            info!("Built-in model has no bookmark file, so bookmarks won't be saved");
+            return;
+        };
+        let file = File {
+            bookmarks: self.list.clone(),
+        };
+        let data = serde_json::to_string_pretty(&file).unwrap();
+        std::fs::write(path, data)
+            .unwrap_or_else(|e| panic!("Failed to write bookmarks {:?}: {}", path, e));
+    }
+
+    /// Returns an unused name for a new bookmark
+    pub fn next_name(&self) -> String {
+        (1..)
+            .map(|i| format!("view-{}", i))
+            .find(|name| self.list.iter().all(|b| b.name != *name))
+            .unwrap()
+    }
+
+    /// Adds a bookmark (replacing any with the same name) and saves the file
+    pub fn add(&mut self, bookmark: Bookmark) {
+        let i = match self.list.iter().position(|b| b.name == bookmark.name) {
+            Some(i) => {
+                self.list[i] = bookmark;
+                i
+            }
+            None => {
+                self.list.push(bookmark);
+                self.list.len() - 1
+            }
+        };
+        self.current = Some(i);
+        self.save();
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Bookmark> {
+        self.list.iter().find(|b| b.name == name)
+    }
+
+    /// Steps forwards or backwards through the list, wrapping around
+    pub fn step(&mut self, delta: isize) -> Option<&Bookmark> {
+        if self.list.is_empty() {
+            return None;
+        }
+        let count = self.list.len() as isize;
+        let i = match self.current {
+            Some(i) => (i as isize + delta).rem_euclid(count) as usize,
+            None if delta < 0 => self.list.len() - 1,
+            None => 0,
+        };
+        self.current = Some(i);
+        Some(&self.list[i])
+    }
+
+    pub fn list(&self) -> &[Bookmark] {
+        &self.list
+    }
+}
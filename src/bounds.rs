@@ -0,0 +1,56 @@
+use glm::Vec3;
+use itertools::Itertools;
+use nalgebra_glm as glm;
+
+use crate::model::GPUVertex;
+
+/// An axis-aligned bounding box
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Returns the bounds of a set of vertices, which must not be empty
+    pub(crate) fn from_verts(verts: &[GPUVertex]) -> Self {
+        let axis = |i: usize| {
+            verts
+                .iter()
+                .map(|v| v.pos[i])
+                .minmax()
+                .into_option()
+                .unwrap()
+        };
+        let (xb, yb, zb) = (axis(0), axis(1), axis(2));
+        Aabb {
+            min: Vec3::new(xb.0, yb.0, zb.0),
+            max: Vec3::new(xb.1, yb.1, zb.1),
+        }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Radius of the bounding sphere around the box's center
+    pub fn radius(&self) -> f32 {
+        self.size().norm() / 2.0
+    }
+
+    /// Returns the eight corners, where bit 0 / 1 / 2 of the index selects
+    /// the max side in X / Y / Z
+    pub fn corners(&self) -> [Vec3; 8] {
+        std::array::from_fn(|i| {
+            Vec3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            )
+        })
+    }
+}
@@ -0,0 +1,275 @@
+use glm::Vec3;
+use nalgebra_glm as glm;
+
+use crate::{bounds::Aabb, model::GPUVertex};
+
+/// Maximum number of triangles in a leaf
+const LEAF_SIZE: usize = 4;
+
+struct Node {
+    bounds: Aabb,
+    /// For leaves, the first triangle in `Bvh::tris`; otherwise, the index
+    /// of the left child (the right child immediately follows it)
+    start: u32,
+    /// Number of triangles in a leaf, or 0 for interior nodes
+    count: u32,
+}
+
+/// A ray hit against the BVH's triangles
+#[derive(Copy, Clone, Debug)]
+pub struct Hit {
+    /// Index of the triangle, i.e. its first index is at `3 * triangle`
+    pub triangle: u32,
+    /// Distance along the ray, in units of the ray direction's length
+    pub t: f32,
+    pub position: Vec3,
+}
+
+/// Bounding volume hierarchy over a triangle mesh, for CPU ray casts
+pub struct Bvh {
+    nodes: Vec<Node>,
+    /// Triangle indices, reordered so that each leaf is a contiguous range
+    tris: Vec<u32>,
+    /// Triangle corners, in the same order as `tris`
+    corners: Vec<[Vec3; 3]>,
+}
+
+impl Bvh {
+    /// Builds a BVH by recursively splitting triangles at the median
+    /// centroid along the longest axis of their bounds
+    pub fn new(vertices: &[GPUVertex], indices: &[u32]) -> Self {
+        let pos = |i: u32| Vec3::from_row_slice(&vertices[i as usize].pos[..3]);
+        let all = indices
+            .chunks_exact(3)
+            .map(|t| [pos(t[0]), pos(t[1]), pos(t[2])])
+            .collect::<Vec<_>>();
+        let centroids = all
+            .iter()
+            .map(|c| (c[0] + c[1] + c[2]) / 3.0)
+            .collect::<Vec<_>>();
+
+        let mut bvh = Bvh {
+            nodes: vec![],
+            tris: (0..all.len() as u32).collect(),
+            corners: vec![],
+        };
+        if !all.is_empty() {
+            bvh.nodes.push(Node {
+                bounds: Self::bounds(&all, &bvh.tris),
+                start: 0,
+                count: all.len() as u32,
+            });
+            bvh.split(0, &all, &centroids);
+        }
+        bvh.corners = bvh.tris.iter().map(|&t| all[t as usize]).collect();
+        bvh
+    }
+
+    fn bounds(corners: &[[Vec3; 3]], tris: &[u32]) -> Aabb {
+        let mut b = Aabb {
+            min: Vec3::repeat(f32::INFINITY),
+            max: Vec3::repeat(f32::NEG_INFINITY),
+        };
+        for p in tris.iter().flat_map(|&t| corners[t as usize].iter()) {
+            b.min = b.min.inf(p);
+            b.max = b.max.sup(p);
+        }
+        b
+    }
+
+    fn split(&mut self, node: usize, corners: &[[Vec3; 3]], centroids: &[Vec3]) {
+        let start = self.nodes[node].start as usize;
+        let count = self.nodes[node].count as usize;
+        if count <= LEAF_SIZE {
+            return;
+        }
+        let axis = self.nodes[node].bounds.size().imax();
+        let mid = count / 2;
+        let tris = &mut self.tris[start..start + count];
+        tris.select_nth_unstable_by(mid, |a, b| {
+            centroids[*a as usize][axis].total_cmp(&centroids[*b as usize][axis])
+        });
+
+        let left = self.nodes.len();
+        for (s, c) in [(start, mid), (start + mid, count - mid)] {
+            self.nodes.push(Node {
+                bounds: Self::bounds(corners, &self.tris[s..s + c]),
+                start: s as u32,
+                count: c as u32,
+            });
+        }
+        self.nodes[node].start = left as u32;
+        self.nodes[node].count = 0;
+        self.split(left, corners, centroids);
+        self.split(left + 1, corners, centroids);
+    }
+
+    /// Returns the closest hit along the ray, if any.  Triangles are hit from
+    /// either side.
+    pub fn cast(&self, origin: Vec3, dir: Vec3) -> Option<Hit> {
+        let inv_dir = Vec3::new(1.0 / dir.x, 1.0 / dir.y, 1.0 / dir.z);
+        let mut best: Option<Hit> = None;
+        let mut stack = vec![0];
+        while let Some(i) = stack.pop() {
+            let Some(node) = self.nodes.get(i) else {
+                continue;
+            };
+            let limit = best.map(|h| h.t).unwrap_or(f32::INFINITY);
+            if !Self::hits_box(&node.bounds, origin, inv_dir, limit) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.start as usize);
+                stack.push(node.start as usize + 1);
+                continue;
+            }
+            let range = node.start as usize..(node.start + node.count) as usize;
+            for (c, &tri) in self.corners[range.clone()].iter().zip(&self.tris[range]) {
+                match Self::hits_triangle(c, origin, dir) {
+                    Some(t) if t < limit && best.map(|h| t < h.t).unwrap_or(true) => {
+                        best = Some(Hit {
+                            triangle: tri,
+                            t,
+                            position: origin + dir * t,
+                        });
+                    }
+                    _ => (),
+                }
+            }
+        }
+        best
+    }
+
+    /// Slab test, returning true if the ray enters the box before `limit`
+    fn hits_box(b: &Aabb, origin: Vec3, inv_dir: Vec3, limit: f32) -> bool {
+        let t0 = (b.min - origin).component_mul(&inv_dir);
+        let t1 = (b.max - origin).component_mul(&inv_dir);
+        let near = t0.inf(&t1).max().max(0.0);
+        let far = t0.sup(&t1).min().min(limit);
+        near <= far
+    }
+
+    /// Möller–Trumbore intersection, returning the distance along the ray
+    fn hits_triangle(c: &[Vec3; 3], origin: Vec3, dir: Vec3) -> Option<f32> {
+        let e1 = c[1] - c[0];
+        let e2 = c[2] - c[0];
+        let p = dir.cross(&e2);
+        let det = e1.dot(&p);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let s = origin - c[0];
+        let u = s.dot(&p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(&e1);
+        let v = dir.dot(&q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = e2.dot(&q) * inv_det;
+        (t >= 0.0).then_some(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(x: f32, y: f32, z: f32) -> GPUVertex {
+        GPUVertex {
+            pos: [x, y, z, 1.0],
+            norm: [0.0, 0.0, 1.0, 0.0],
+            color: [1.0; 4],
+            uv: [0.0; 2],
+        }
+    }
+
+    /// Adds a 2x2 square facing +z, centered on the z axis, as two triangles
+    fn add_square(vertices: &mut Vec<GPUVertex>, indices: &mut Vec<u32>, z: f32) {
+        let base = vertices.len() as u32;
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            vertices.push(vertex(x, y, z));
+        }
+        indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+    }
+
+    fn down(x: f32, y: f32) -> (Vec3, Vec3) {
+        (Vec3::new(x, y, 10.0), Vec3::new(0.0, 0.0, -1.0))
+    }
+
+    #[test]
+    fn hits_and_misses() {
+        let (mut vertices, mut indices) = (vec![], vec![]);
+        add_square(&mut vertices, &mut indices, 0.0);
+        let bvh = Bvh::new(&vertices, &indices);
+
+        let (origin, dir) = down(0.25, 0.5);
+        let hit = bvh.cast(origin, dir).expect("ray through the square should hit");
+        assert_eq!(hit.triangle, 1);
+        assert!((hit.t - 10.0).abs() < 1e-5);
+        assert!((hit.position - Vec3::new(0.25, 0.5, 0.0)).norm() < 1e-5);
+
+        // Beside the square, and pointing away from it
+        let (origin, dir) = down(1.5, 0.5);
+        assert!(bvh.cast(origin, dir).is_none());
+        let (origin, dir) = down(0.25, 0.5);
+        assert!(bvh.cast(origin, -dir).is_none());
+    }
+
+    #[test]
+    fn returns_the_nearest_hit() {
+        // Enough squares to split into several leaves
+        let (mut vertices, mut indices) = (vec![], vec![]);
+        for z in [3.0, -2.0, 5.0, 0.0, 4.0, -1.0, 1.0, 2.0] {
+            add_square(&mut vertices, &mut indices, z);
+        }
+        let bvh = Bvh::new(&vertices, &indices);
+        assert!(bvh.nodes.len() > 1);
+
+        let (origin, dir) = down(0.25, -0.5);
+        let hit = bvh.cast(origin, dir).unwrap();
+        assert!((hit.position.z - 5.0).abs() < 1e-5);
+        assert_eq!(hit.triangle / 2, 2);
+
+        let hit = bvh.cast(Vec3::new(0.25, -0.5, -10.0), -dir).unwrap();
+        assert!((hit.position.z + 2.0).abs() < 1e-5);
+        assert_eq!(hit.triangle / 2, 1);
+
+        // Starting between squares, only those in front count
+        let hit = bvh.cast(Vec3::new(0.25, -0.5, 0.5), dir).unwrap();
+        assert!(hit.position.z.abs() < 1e-5);
+        assert_eq!(hit.triangle / 2, 3);
+    }
+
+    #[test]
+    fn empty_mesh_never_hits() {
+        let bvh = Bvh::new(&[], &[]);
+        let (origin, dir) = down(0.0, 0.0);
+        assert!(bvh.cast(origin, dir).is_none());
+    }
+
+    #[test]
+    fn skips_degenerate_triangles() {
+        // Zero-area triangles, all with the same centroid, so that splitting
+        // them can't separate anything and leaves end up flat
+        let mut vertices = vec![
+            vertex(-1.0, 0.25, 1.0),
+            vertex(0.0, 0.25, 1.0),
+            vertex(1.0, 0.25, 1.0),
+        ];
+        let mut indices = [0, 1, 2].repeat(9);
+        let bvh = Bvh::new(&vertices, &indices);
+        let (origin, dir) = down(0.0, 0.25);
+        assert!(bvh.cast(origin, dir).is_none());
+
+        // A real triangle behind them is still found
+        add_square(&mut vertices, &mut indices, -1.0);
+        let bvh = Bvh::new(&vertices, &indices);
+        let hit = bvh.cast(origin, dir).unwrap();
+        assert!((hit.position.z + 1.0).abs() < 1e-5);
+        assert!(hit.triangle >= 9);
+    }
+}
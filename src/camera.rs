@@ -1,9 +1,16 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
 use glm::{Mat4, Vec2, Vec3, Vec4};
-use itertools::Itertools;
 use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
 use crate::winit::event::MouseButton;
 
-use crate::model::GPUVertex;
+use crate::{
+    bookmarks::Bookmark,
+    bounds::Aabb,
+    model::SceneCamera,
+    time::Instant,
+};
 
 #[derive(Copy, Clone, Debug)]
 enum MouseState {
@@ -13,6 +20,67 @@ enum MouseState {
     Pan(Vec2, Vec3),
 }
 
+/// How the scene is projected onto the screen
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Projection {
+    Orthographic,
+    Perspective,
+}
+
+/// How mouse and keyboard input move the camera
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Rotates around the view center
+    Orbit,
+    /// Rotates around the eye, and moves with WASD
+    Fly,
+}
+
+/// Fixed viewing directions, which can be jumped to from the keyboard
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PresetView {
+    Front,
+    Top,
+    Side,
+}
+
+/// Distance from the eye to the view center in perspective mode, in units
+/// where the fitted model is 1 wide
+const PERSPECTIVE_DISTANCE: f32 = 3.0;
+const PERSPECTIVE_NEAR: f32 = 0.001;
+
+/// Duration of eased transitions between views, in seconds
+const TRANSITION_TIME: f32 = 0.4;
+/// Rate at which rotation keeps going after a drag is released, i.e. the
+/// velocity decays by a factor of e every `1 / INERTIA_DAMPING` seconds
+const INERTIA_DAMPING: f32 = 4.0;
+/// Rate at which smoothed scroll zooming catches up with the wheel
+const ZOOM_RATE: f32 = 15.0;
+/// A drag which pauses for this long before release doesn't keep spinning
+const INERTIA_HOLD: f32 = 0.05;
+
+/// Fly speed, in fitted model widths per second
+const FLY_SPEED: f32 = 0.5;
+/// Speed multiplier while the boost key is held
+const FLY_BOOST: f32 = 4.0;
+
+/// The parts of the camera state which are eased between views
+#[derive(Copy, Clone, Debug)]
+struct Pose {
+    pitch: f32,
+    yaw: f32,
+    scale: f32,
+    center: Vec3,
+}
+
+struct Transition {
+    from: Pose,
+    to: Pose,
+    /// Progress from 0 to 1
+    t: f32,
+}
+
 pub struct Camera {
     /// Aspect ratio of the window
     width: f32,
@@ -30,7 +98,29 @@ pub struct Camera {
     /// Center of view volume
     center: Vec3,
 
+    projection: Projection,
+
+    mode: CameraMode,
+
+    /// Multiplier on the fly speed, adjusted with the scroll wheel
+    fly_speed: f32,
+
     mouse: MouseState,
+
+    /// Whether rotation keeps going after a drag and scrolling is smoothed
+    inertia: bool,
+    /// Drag velocity in normalized device coordinates per second, applied
+    /// after release if inertia is on
+    velocity: Vec2,
+    /// Time of the last drag movement, for estimating velocity
+    drag_time: Option<Instant>,
+    /// Set while a finger is rotating the view
+    touch_drag: bool,
+    /// Zoom which has yet to be applied, as a log scale factor, and the
+    /// normalized position to zoom around
+    pending_zoom: (f32, Vec2),
+
+    transition: Option<Transition>,
 }
 
 impl Camera {
@@ -42,11 +132,132 @@ impl Camera {
             yaw: 0.0,
             scale: 1.0,
             center: Vec3::zeros(),
+            projection: Projection::Orthographic,
+            mode: CameraMode::Orbit,
+            fly_speed: 1.0,
             mouse: MouseState::Unknown,
+            inertia: false,
+            velocity: Vec2::zeros(),
+            drag_time: None,
+            touch_drag: false,
+            pending_zoom: (0.0, Vec2::zeros()),
+            transition: None,
         }
     }
 
+    pub fn set_inertia(&mut self, inertia: bool) {
+        self.inertia = inertia;
+    }
+
+    /// Stops any transition or leftover motion, so that direct input takes
+    /// over
+    fn interrupt(&mut self) {
+        self.transition = None;
+        self.velocity = Vec2::zeros();
+        self.pending_zoom.0 = 0.0;
+    }
+
+    fn dragging(&self) -> bool {
+        matches!(self.mouse, MouseState::Rotate(..)) || self.touch_drag
+    }
+
+    /// Called when a rotating drag is released.  If the pointer was held
+    /// still beforehand, then it shouldn't keep spinning.
+    fn end_drag(&mut self) {
+        let held = self
+            .drag_time
+            .map(|t| t.elapsed().as_secs_f32() > INERTIA_HOLD)
+            .unwrap_or(true);
+        if held {
+            self.velocity = Vec2::zeros();
+        }
+        self.drag_time = None;
+    }
+
+    /// Marks a one-finger rotation as starting or ending
+    pub fn set_touch_drag(&mut self, dragging: bool) {
+        if dragging {
+            self.interrupt();
+        } else if self.touch_drag {
+            self.end_drag();
+        }
+        self.touch_drag = dragging;
+    }
+
+    /// Advances inertia and transitions by a time step in seconds
+    pub fn update(&mut self, dt: f32) {
+        if let Some(tr) = &mut self.transition {
+            tr.t = (tr.t + dt / TRANSITION_TIME).min(1.0);
+            // Smoothstep easing
+            let e = tr.t * tr.t * (3.0 - 2.0 * tr.t);
+            let (from, to, done) = (tr.from, tr.to, tr.t >= 1.0);
+            self.set_pose(Pose {
+                pitch: glm::lerp_scalar(from.pitch, to.pitch, e),
+                yaw: glm::lerp_scalar(from.yaw, to.yaw, e),
+                scale: from.scale * (to.scale / from.scale).powf(e),
+                center: glm::lerp(&from.center, &to.center, e),
+            });
+            if done {
+                self.transition = None;
+            }
+        }
+
+        if !self.dragging() && self.velocity != Vec2::zeros() {
+            self.drag_rotate(self.velocity * dt);
+            self.velocity *= (-INERTIA_DAMPING * dt).exp();
+            if self.velocity.norm() < 1e-3 {
+                self.velocity = Vec2::zeros();
+            }
+        }
+
+        let (zoom, pos) = self.pending_zoom;
+        if zoom != 0.0 {
+            let step = if zoom.abs() < 1e-4 {
+                zoom
+            } else {
+                zoom * (1.0 - (-ZOOM_RATE * dt).exp())
+            };
+            self.scale(step.exp(), pos);
+            self.pending_zoom.0 -= step;
+        }
+    }
+
+    /// Returns true if the camera is moving by itself, so frames should keep
+    /// coming
+    pub fn is_animating(&self) -> bool {
+        self.transition.is_some()
+            || (!self.dragging() && self.velocity != Vec2::zeros())
+            || self.pending_zoom.0 != 0.0
+    }
+
+    fn pose(&self) -> Pose {
+        Pose {
+            pitch: self.pitch,
+            yaw: self.yaw,
+            scale: self.scale,
+            center: self.center,
+        }
+    }
+
+    fn set_pose(&mut self, pose: Pose) {
+        self.pitch = pose.pitch;
+        self.yaw = pose.yaw;
+        self.scale = pose.scale;
+        self.center = pose.center;
+    }
+
+    /// Eases from the current pose to a new one, taking the short way around
+    fn transition_to(&mut self, mut to: Pose) {
+        self.interrupt();
+        let from = self.pose();
+        let wrap = |a: f32| (a + PI).rem_euclid(TAU) - PI;
+        to.pitch = from.pitch + wrap(to.pitch - from.pitch);
+        to.yaw = from.yaw + wrap(to.yaw - from.yaw);
+        self.transition = Some(Transition { from, to, t: 0.0 });
+    }
+
     pub fn mouse_pressed(&mut self, button: MouseButton) {
+        self.interrupt();
         // If we were previously free, then switch to panning or rotating
         if let MouseState::Free(pos) = &self.mouse {
             match button {
@@ -58,6 +269,9 @@ impl Camera {
         }
     }
     pub fn mouse_released(&mut self, button: MouseButton) {
+        if button == MouseButton::Left && matches!(self.mouse, MouseState::Rotate(..)) {
+            self.end_drag();
+        }
         match &self.mouse {
             MouseState::Rotate(pos) if button == MouseButton::Left => Some(MouseState::Free(*pos)),
             MouseState::Pan(pos, ..) if button == MouseButton::Right => {
@@ -78,15 +292,45 @@ impl Camera {
             .expect("Failed to invert mouse matrix")
     }
 
-    /// Converts a normalized mouse position into 3D
+    /// Converts a point in normalized device coordinates into 3D
+    fn unproject(&self, ndc: Vec3) -> Vec3 {
+        let p = self.mat_i() * ndc.push(1.0);
+        p.xyz() / p.w
+    }
+
+    /// Returns the depth (in normalized device coordinates) of the plane
+    /// through the view center
+    fn focal_depth(&self) -> f32 {
+        match self.projection {
+            Projection::Orthographic => 0.5,
+            Projection::Perspective => PERSPECTIVE_NEAR / PERSPECTIVE_DISTANCE,
+        }
+    }
+
+    /// Converts a normalized mouse position into 3D, on the plane through
+    /// the view center
     pub fn mouse_pos(&self, pos_norm: Vec2) -> Vec3 {
-        (self.mat_i() * Vec4::new(pos_norm.x, pos_norm.y, 0.0, 1.0)).xyz()
+        self.unproject(pos_norm.push(self.focal_depth()))
+    }
+
+    /// Converts a position in pixels into normalized device coordinates
+    fn normalize(&self, px: Vec2) -> Vec2 {
+        let x_norm = 2.0 * (px.x / self.width - 0.5);
+        let y_norm = -2.0 * (px.y / self.height - 0.5);
+        Vec2::new(x_norm, y_norm)
+    }
+
+    /// Returns a world-space ray through a position in pixels, as an origin
+    /// at the front of the view volume and a direction towards the view center
+    pub fn ray(&self, px: Vec2) -> (Vec3, Vec3) {
+        let pos = self.normalize(px);
+        let front = self.unproject(pos.push(1.0));
+        let focus = self.mouse_pos(pos);
+        (front, focus - front)
     }
 
     pub fn mouse_move(&mut self, new_pos: Vec2) {
-        let x_norm = 2.0 * (new_pos.x / self.width - 0.5);
-        let y_norm = -2.0 * (new_pos.y / self.height - 0.5);
-        let new_pos = Vec2::new(x_norm, y_norm);
+        let new_pos = self.normalize(new_pos);
 
         // Pan or rotate depending on current mouse state
         match &self.mouse {
@@ -95,10 +339,7 @@ impl Camera {
                 let delta_pos = orig - current_pos;
                 self.center += delta_pos;
             }
-            MouseState::Rotate(pos) => {
-                let delta = new_pos - *pos;
-                self.spin(delta.x * 3.0, -delta.y * 3.0 * self.height / self.width);
-            }
+            MouseState::Rotate(pos) => self.drag(new_pos - *pos),
             _ => (),
         }
 
@@ -111,40 +352,238 @@ impl Camera {
         }
     }
 
+    /// Tracks the velocity of a rotating drag, then applies it
+    fn drag(&mut self, delta: Vec2) {
+        if self.inertia {
+            let now = Instant::now();
+            let dt = self.drag_time.map(|t| (now - t).as_secs_f32());
+            self.velocity = match dt {
+                // Smooth out the estimate, since pointer events are jittery
+                Some(dt) if dt < INERTIA_HOLD * 2.0 => {
+                    glm::lerp(&self.velocity, &(delta / dt.max(1e-3)), 0.6)
+                }
+                _ => Vec2::zeros(),
+            };
+            self.drag_time = Some(now);
+        }
+        self.drag_rotate(delta);
+    }
+
+    /// Rotates for a drag of `delta` in normalized device coordinates, i.e.
+    /// orbiting or looking around depending on the mode
+    fn drag_rotate(&mut self, delta: Vec2) {
+        let (dx, dy) = (delta.x * 3.0, -delta.y * 3.0 * self.height / self.width);
+        match self.mode {
+            CameraMode::Orbit => self.spin(dx, dy),
+            CameraMode::Fly => self.look(-dx, -dy),
+        }
+    }
+
+    /// Rotates for a one-finger drag between two positions in pixels
+    pub fn touch_rotate(&mut self, from: Vec2, to: Vec2) {
+        self.drag(self.normalize(to) - self.normalize(from));
+    }
+
+    /// Pans so that the point under `from` (in pixels) ends up under `to`
+    pub fn touch_pan(&mut self, from: Vec2, to: Vec2) {
+        self.interrupt();
+        self.center += self.mouse_pos(self.normalize(from)) - self.mouse_pos(self.normalize(to));
+    }
+
+    /// Zooms by a factor, keeping the point under `px` (in pixels) in place
+    pub fn touch_zoom(&mut self, factor: f32, px: Vec2) {
+        self.interrupt();
+        match self.mode {
+            CameraMode::Orbit => self.scale(factor, self.normalize(px)),
+            CameraMode::Fly => self.fly_speed *= factor,
+        }
+    }
+
+    /// Turns around the vertical axis by an angle in radians, for twist
+    /// gestures
+    pub fn twist(&mut self, angle: f32) {
+        self.interrupt();
+        match self.mode {
+            CameraMode::Orbit => self.spin(angle, 0.0),
+            CameraMode::Fly => self.look(-angle, 0.0),
+        }
+    }
+
     pub fn mouse_scroll(&mut self, delta: f32) {
         if let MouseState::Free(pos) = self.mouse {
-            self.scale(1.0 + delta / 200.0, pos);
-        }
-    }
-
-    pub fn fit_verts(&mut self, verts: &[GPUVertex]) {
-        let xb = verts
-            .iter()
-            .map(|v| v.pos[0])
-            .minmax()
-            .into_option()
-            .unwrap();
-        let yb = verts
-            .iter()
-            .map(|v| v.pos[1])
-            .minmax()
-            .into_option()
-            .unwrap();
-        let zb = verts
-            .iter()
-            .map(|v| v.pos[2])
-            .minmax()
-            .into_option()
-            .unwrap();
-        let dx = xb.1 - xb.0;
-        let dy = yb.1 - yb.0;
-        let dz = zb.1 - zb.0;
-        self.scale = (1.0 / dx.max(dy).max(dz)) as f32;
-        self.center = Vec3::new(
-            (xb.0 + xb.1) as f32 / 2.0,
-            (yb.0 + yb.1) as f32 / 2.0,
-            (zb.0 + zb.1) as f32 / 2.0,
-        );
+            self.transition = None;
+            match self.mode {
+                CameraMode::Orbit if self.inertia => {
+                    self.pending_zoom = (self.pending_zoom.0 + (1.0 + delta / 200.0).ln(), pos);
+                }
+                CameraMode::Orbit => self.scale(1.0 + delta / 200.0, pos),
+                CameraMode::Fly => self.fly_speed *= 1.0 + delta / 200.0,
+            }
+        }
+    }
+
+    /// Fits the view around the given bounds.  Unlike `fit`, this jumps
+    /// straight there.
+    pub fn fit_now(&mut self, bounds: &Aabb) {
+        self.set_pose(self.fitted(bounds));
+    }
+
+    /// Returns the current pose, centered and scaled around the given bounds
+    fn fitted(&self, bounds: &Aabb) -> Pose {
+        let size = bounds.size();
+        Pose {
+            scale: 1.0 / size.x.max(size.y).max(size.z),
+            center: bounds.center(),
+            ..self.pose()
+        }
+    }
+
+    /// Centers and scales the view around the given bounds, keeping the
+    /// current rotation
+    pub fn fit(&mut self, bounds: &Aabb) {
+        self.transition_to(self.fitted(bounds));
+    }
+
+    /// Returns to the initial front view, fitted around the given bounds
+    pub fn reset(&mut self, bounds: &Aabb) {
+        let (pitch, yaw) = Self::preset_angles(PresetView::Front);
+        self.transition_to(Pose {
+            pitch,
+            yaw,
+            ..self.fitted(bounds)
+        });
+    }
+
+    fn preset_angles(view: PresetView) -> (f32, f32) {
+        match view {
+            PresetView::Front => (0.0, 0.0),
+            PresetView::Top => (0.0, FRAC_PI_2),
+            PresetView::Side => (-FRAC_PI_2, 0.0),
+        }
+    }
+
+    pub fn set_preset(&mut self, view: PresetView) {
+        let (pitch, yaw) = Self::preset_angles(view);
+        self.transition_to(Pose {
+            pitch,
+            yaw,
+            ..self.pose()
+        });
+    }
+
+    /// Captures the current view as a bookmark
+    pub fn bookmark(&self, name: String) -> Bookmark {
+        Bookmark {
+            name,
+            center: self.center.into(),
+            scale: self.scale,
+            pitch: self.pitch,
+            yaw: self.yaw,
+            projection: self.projection,
+        }
+    }
+
+    /// Moves to a bookmarked view, either easing there or jumping straight
+    /// there
+    pub fn go_to_bookmark(&mut self, bookmark: &Bookmark, ease: bool) {
+        self.projection = bookmark.projection;
+        let pose = Pose {
+            pitch: bookmark.pitch,
+            yaw: bookmark.yaw,
+            scale: bookmark.scale,
+            center: bookmark.center.into(),
+        };
+        if ease {
+            self.transition_to(pose);
+        } else {
+            self.interrupt();
+            self.set_pose(pose);
+        }
+    }
+
+    /// Moves to a camera from the glTF scene.  Its roll is dropped, and the
+    /// view center is placed level with the middle of `bounds`.
+    pub fn go_to(&mut self, camera: &SceneCamera, bounds: &Aabb) {
+        let eye = (camera.transform * Vec4::new(0.0, 0.0, 0.0, 1.0)).xyz();
+        let forward = (camera.transform * Vec4::new(0.0, 0.0, -1.0, 0.0))
+            .xyz()
+            .normalize();
+        // Solve eye_dir() = -forward for the two Euler angles
+        let back = -forward;
+        let yaw = back.y.clamp(-1.0, 1.0).asin();
+        let pitch = (-back.x).atan2(back.z);
+
+        let distance = (bounds.center() - eye)
+            .dot(&forward)
+            .max(bounds.radius() * 0.1);
+        let scale = match camera.xmag {
+            Some(xmag) => {
+                self.projection = Projection::Orthographic;
+                1.0 / xmag
+            }
+            None => {
+                self.projection = Projection::Perspective;
+                PERSPECTIVE_DISTANCE / distance
+            }
+        };
+        self.transition_to(Pose {
+            pitch,
+            yaw,
+            scale,
+            center: eye + forward * distance,
+        });
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Switches between orbiting and flying.  Flying only makes sense with
+    /// perspective, so that's switched on too.
+    pub fn toggle_fly(&mut self) {
+        self.mode = match self.mode {
+            CameraMode::Orbit => {
+                self.projection = Projection::Perspective;
+                CameraMode::Fly
+            }
+            CameraMode::Fly => CameraMode::Orbit,
+        };
+    }
+
+    /// Returns the eye position in perspective mode, in world space
+    fn eye(&self) -> Vec3 {
+        self.center + self.eye_dir() * PERSPECTIVE_DISTANCE / self.scale
+    }
+
+    /// Rotates the view around the eye, rather than around the view center
+    fn look(&mut self, dx: f32, dy: f32) {
+        let eye = self.eye();
+        self.spin(dx, dy);
+        self.center = eye - self.eye_dir() * PERSPECTIVE_DISTANCE / self.scale;
+    }
+
+    /// Moves the eye in fly mode.  `dir` is in view space (X right, Y up and
+    /// Z towards the viewer), and `dt` is the frame time in seconds.
+    pub fn fly(&mut self, dir: Vec3, boost: bool, dt: f32) {
+        if self.mode != CameraMode::Fly || dir == Vec3::zeros() {
+            return;
+        }
+        self.transition = None;
+        let boost = if boost { FLY_BOOST } else { 1.0 };
+        let speed = FLY_SPEED * self.fly_speed * boost / self.scale;
+        let world = (self.rotation_matrix().transpose() * dir.normalize().to_homogeneous()).xyz();
+        self.center += world * speed * dt;
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn toggle_projection(&mut self) {
+        self.projection = match self.projection {
+            Projection::Orthographic => Projection::Perspective,
+            Projection::Perspective => Projection::Orthographic,
+        };
     }
 
     pub fn set_size(&mut self, width: f32, height: f32) {
@@ -161,22 +600,49 @@ impl Camera {
         glm::scale(&i, &Vec3::new(self.scale, self.scale, self.scale)) *
 
         // Rotation!
-        glm::rotate_x(&i, self.yaw) *
-        glm::rotate_y(&i, self.pitch) *
+        self.rotation_matrix() *
 
         // Recenter model
         glm::translate(&i, &-self.center)
     }
 
+    /// Returns the orbit rotation applied to the model
+    pub fn rotation_matrix(&self) -> Mat4 {
+        let i = Mat4::identity();
+        glm::rotate_x(&i, self.yaw) * glm::rotate_y(&i, self.pitch)
+    }
+
+    /// Returns the world-space direction pointing back towards the viewer
+    pub fn eye_dir(&self) -> Vec3 {
+        (self.rotation_matrix().transpose() * Vec4::new(0.0, 0.0, 1.0, 0.0)).xyz()
+    }
+
+    /// Returns the window aspect ratio (width / height)
+    pub fn aspect(&self) -> f32 {
+        self.width / self.height
+    }
+
     /// Returns a matrix which compensates for window aspect ratio and clipping
     pub fn view_matrix(&self) -> Mat4 {
         let i = Mat4::identity();
-        // The Z clipping range is 0-1, so push forward
-        glm::translate(&i, &Vec3::new(0.0, 0.0, 0.5)) *
+        match self.projection {
+            Projection::Orthographic => {
+                // The Z clipping range is 0-1, so push forward
+                glm::translate(&i, &Vec3::new(0.0, 0.0, 0.5)) *
 
-        // Scale to compensate for aspect ratio and reduce Z scale to improve
-        // clipping
-        glm::scale(&i, &Vec3::new(1.0, self.width / self.height, 0.1))
+                // Scale to compensate for aspect ratio and reduce Z scale to
+                // improve clipping
+                glm::scale(&i, &Vec3::new(1.0, self.width / self.height, 0.1))
+            }
+            Projection::Perspective => {
+                // The field of view is picked so that the plane through the
+                // view center is framed exactly as in orthographic mode
+                let aspect = self.aspect();
+                let fovy = 2.0 * (1.0 / (aspect * PERSPECTIVE_DISTANCE)).atan();
+                glm::reversed_infinite_perspective_rh_zo(aspect, fovy, PERSPECTIVE_NEAR)
+                    * glm::translate(&i, &Vec3::new(0.0, 0.0, -PERSPECTIVE_DISTANCE))
+            }
+        }
     }
 
     pub fn spin(&mut self, dx: f32, dy: f32) {
@@ -0,0 +1,402 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::texture::Texture;
+
+/// Size of the cubemap which the equirectangular map is resampled into
+const CUBE_SIZE: u32 = 512;
+
+/// Size of the base level of the prefiltered specular cubemap
+const SPECULAR_SIZE: u32 = 128;
+
+/// Number of roughness levels in the specular cubemap
+pub const SPECULAR_MIPS: u32 = 5;
+
+const IRRADIANCE_SIZE: u32 = 32;
+const BRDF_LUT_SIZE: u32 = 256;
+
+const ENV_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Uniforms for the compute passes, which must match `Params` in
+/// `environment.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Params {
+    roughness: f32,
+    size: u32,
+    src_size: u32,
+    src_mips: u32,
+}
+
+/// Image-based lighting data: a diffuse irradiance cubemap, a specular
+/// cubemap prefiltered by roughness (one level per mip), and the split-sum
+/// BRDF lookup table.
+pub struct Environment {
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Environment {
+    /// Builds lighting from an equirectangular HDR texture, or from a neutral
+    /// procedural sky if none is given
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, equirect: Option<&Texture>) -> Self {
+        let default_sky;
+        let equirect = match equirect {
+            Some(equirect) => equirect,
+            None => {
+                default_sky = Self::default_sky(device, queue);
+                &default_sky
+            }
+        };
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("environment.wgsl"))),
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &shader,
+                entry_point,
+            })
+        };
+        let equirect_to_cube = pipeline("equirect_to_cube");
+        let downsample_cube = pipeline("downsample_cube");
+        let convolve_irradiance = pipeline("convolve_irradiance");
+        let prefilter_specular = pipeline("prefilter_specular");
+        let integrate_brdf = pipeline("integrate_brdf");
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let cube_mips = CUBE_SIZE.ilog2() + 1;
+        let cube = Self::cube_texture(device, "environment cube", CUBE_SIZE, cube_mips);
+        let specular = Self::cube_texture(device, "specular cube", SPECULAR_SIZE, SPECULAR_MIPS);
+        let irradiance = Self::cube_texture(device, "irradiance cube", IRRADIANCE_SIZE, 1);
+        let brdf_lut = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("brdf lut"),
+            size: wgpu::Extent3d {
+                width: BRDF_LUT_SIZE,
+                height: BRDF_LUT_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ENV_FORMAT,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let cube_view = cube.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let params = |roughness: f32, size: u32| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::bytes_of(&Params {
+                    roughness,
+                    size,
+                    src_size: CUBE_SIZE,
+                    src_mips: cube_mips,
+                }),
+                usage: wgpu::BufferUsages::UNIFORM,
+            })
+        };
+        let bind = |pipeline: &wgpu::ComputePipeline, entries: &[wgpu::BindGroupEntry]| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries,
+            })
+        };
+        let workgroups = |size: u32| size.div_ceil(8);
+
+        // Each step is a pipeline, its bind group, and the size (in texels)
+        // and layer count of the dispatch
+        let mut steps = Vec::new();
+
+        // Resample the equirectangular map into the base cubemap level
+        let buf = params(0.0, CUBE_SIZE);
+        let bind_group = bind(
+            &equirect_to_cube,
+            &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&equirect.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&Self::mip_view(&cube, 0)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: buf.as_entire_binding(),
+                },
+            ],
+        );
+        steps.push((&equirect_to_cube, bind_group, CUBE_SIZE, 6));
+
+        // Build the mip chain, which is used to reduce noise when filtering
+        for mip in 1..cube_mips {
+            let size = CUBE_SIZE >> mip;
+            let buf = params(0.0, size);
+            let bind_group = bind(
+                &downsample_cube,
+                &[
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&Self::mip_view(&cube, mip)),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: buf.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 6,
+                        resource: wgpu::BindingResource::TextureView(&Self::mip_view(
+                            &cube,
+                            mip - 1,
+                        )),
+                    },
+                ],
+            );
+            steps.push((&downsample_cube, bind_group, size, 6));
+        }
+
+        // Convolve into the diffuse irradiance map
+        let buf = params(0.0, IRRADIANCE_SIZE);
+        let bind_group = bind(
+            &convolve_irradiance,
+            &[
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&cube_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&Self::mip_view(&irradiance, 0)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: buf.as_entire_binding(),
+                },
+            ],
+        );
+        steps.push((&convolve_irradiance, bind_group, IRRADIANCE_SIZE, 6));
+
+        // Prefilter one specular mip per roughness level
+        for mip in 0..SPECULAR_MIPS {
+            let size = SPECULAR_SIZE >> mip;
+            let roughness = mip as f32 / (SPECULAR_MIPS - 1) as f32;
+            let buf = params(roughness, size);
+            let bind_group = bind(
+                &prefilter_specular,
+                &[
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&cube_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&Self::mip_view(
+                            &specular, mip,
+                        )),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: buf.as_entire_binding(),
+                    },
+                ],
+            );
+            steps.push((&prefilter_specular, bind_group, size, 6));
+        }
+
+        // Integrate the BRDF lookup table, which is independent of the
+        // environment itself
+        let buf = params(0.0, BRDF_LUT_SIZE);
+        let bind_group = bind(
+            &integrate_brdf,
+            &[
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(
+                        &brdf_lut.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: buf.as_entire_binding(),
+                },
+            ],
+        );
+        steps.push((&integrate_brdf, bind_group, BRDF_LUT_SIZE, 1));
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("environment"),
+                timestamp_writes: None,
+            });
+            for (pipeline, bind_group, size, layers) in &steps {
+                cpass.set_pipeline(pipeline);
+                cpass.set_bind_group(0, bind_group, &[]);
+                cpass.dispatch_workgroups(workgroups(*size), workgroups(*size), *layers);
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("environment"),
+            entries: &[
+                Self::layout_entry(0, wgpu::TextureViewDimension::Cube),
+                Self::layout_entry(1, wgpu::TextureViewDimension::Cube),
+                Self::layout_entry(2, wgpu::TextureViewDimension::D2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let as_cube = |tex: &wgpu::Texture| {
+            tex.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                ..Default::default()
+            })
+        };
+        let lighting_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("environment"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&as_cube(&irradiance)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&as_cube(&specular)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &brdf_lut.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&lighting_sampler),
+                },
+            ],
+        });
+
+        Environment {
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Builds a soft sky-over-ground equirectangular map, so that models are
+    /// lit sensibly when no environment is given
+    fn default_sky(device: &wgpu::Device, queue: &wgpu::Queue) -> Texture {
+        let (width, height) = (128, 64);
+        let mut texels = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            // Elevation, from 1 (straight up) to -1 (straight down)
+            let e = 1.0 - 2.0 * (y as f32 + 0.5) / height as f32;
+            let c = if e >= 0.0 {
+                let t = e.powf(0.5);
+                [0.9 - 0.3 * t, 0.95 - 0.2 * t, 1.0]
+            } else {
+                let t = (-e).powf(0.5);
+                [0.35 - 0.15 * t, 0.33 - 0.15 * t, 0.3 - 0.15 * t]
+            };
+            for _ in 0..width {
+                texels.extend_from_slice(&[c[0], c[1], c[2], 1.0]);
+            }
+        }
+        Texture::from_rgba_f32(device, queue, width as u32, height as u32, &texels)
+    }
+
+    fn cube_texture(device: &wgpu::Device, label: &str, size: u32, mips: u32) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: mips,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ENV_FORMAT,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    /// Returns all six faces of a single cubemap mip, as an array view
+    fn mip_view(tex: &wgpu::Texture, mip: u32) -> wgpu::TextureView {
+        tex.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            base_mip_level: mip,
+            mip_level_count: Some(1),
+            ..Default::default()
+        })
+    }
+
+    fn layout_entry(
+        binding: u32,
+        view_dimension: wgpu::TextureViewDimension,
+    ) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension,
+                multisampled: false,
+            },
+            count: None,
+        }
+    }
+}
@@ -0,0 +1,226 @@
+// Compute passes which turn an equirectangular HDR map into the textures
+// used for image-based lighting.  Each entry point uses its own subset of
+// bindings, and gets an automatically-derived bind group layout.
+
+struct Params {
+    // Roughness of the specular mip being filtered
+    roughness: f32,
+    // Width (and height) of the face being written
+    size: u32,
+    // Width of the source cubemap's base level
+    src_size: u32,
+    // Number of mips in the source cubemap
+    src_mips: u32,
+};
+
+@group(0) @binding(0)
+var src_equirect: texture_2d<f32>;
+@group(0) @binding(1)
+var src_sampler: sampler;
+@group(0) @binding(2)
+var src_cube: texture_cube<f32>;
+@group(0) @binding(3)
+var dst_cube: texture_storage_2d_array<rgba16float, write>;
+@group(0) @binding(4)
+var dst_lut: texture_storage_2d<rgba16float, write>;
+@group(0) @binding(5)
+var<uniform> params: Params;
+@group(0) @binding(6)
+var src_level: texture_2d_array<f32>;
+
+const PI: f32 = 3.14159265359;
+
+// Returns the direction through the center of a cubemap texel, following
+// the WebGPU face ordering (+X, -X, +Y, -Y, +Z, -Z)
+fn cube_dir(id: vec3<u32>, size: u32) -> vec3<f32> {
+    let uv = (vec2<f32>(id.xy) + 0.5) / f32(size) * 2.0 - 1.0;
+    var dir: vec3<f32>;
+    switch id.z {
+        case 0u: { dir = vec3<f32>(1.0, -uv.y, -uv.x); }
+        case 1u: { dir = vec3<f32>(-1.0, -uv.y, uv.x); }
+        case 2u: { dir = vec3<f32>(uv.x, 1.0, uv.y); }
+        case 3u: { dir = vec3<f32>(uv.x, -1.0, -uv.y); }
+        case 4u: { dir = vec3<f32>(uv.x, -uv.y, 1.0); }
+        default: { dir = vec3<f32>(-uv.x, -uv.y, -1.0); }
+    }
+    return normalize(dir);
+}
+
+// Must match the lookup in backdrop.wgsl
+fn equirect_uv(dir: vec3<f32>) -> vec2<f32> {
+    return vec2<f32>(
+        atan2(dir.x, -dir.z) / (2.0 * PI) + 0.5,
+        acos(clamp(dir.y, -1.0, 1.0)) / PI,
+    );
+}
+
+fn radical_inverse_vdc(bits_in: u32) -> f32 {
+    var bits = (bits_in << 16u) | (bits_in >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return f32(bits) * 2.3283064365386963e-10;
+}
+
+fn hammersley(i: u32, n: u32) -> vec2<f32> {
+    return vec2<f32>(f32(i) / f32(n), radical_inverse_vdc(i));
+}
+
+// Samples a GGX half-vector around the normal n
+fn importance_sample_ggx(xi: vec2<f32>, n: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    let h = vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+    var up = vec3<f32>(1.0, 0.0, 0.0);
+    if (abs(n.z) < 0.999) {
+        up = vec3<f32>(0.0, 0.0, 1.0);
+    }
+    let tangent = normalize(cross(up, n));
+    let bitangent = cross(n, tangent);
+    return normalize(tangent * h.x + bitangent * h.y + n * h.z);
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+fn geometry_smith_ibl(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    let gv = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let gl = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return gv * gl;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn equirect_to_cube(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.size || id.y >= params.size) {
+        return;
+    }
+    let dir = cube_dir(id, params.size);
+    let color = textureSampleLevel(src_equirect, src_sampler, equirect_uv(dir), 0.0);
+    textureStore(dst_cube, id.xy, id.z, vec4<f32>(color.rgb, 1.0));
+}
+
+// Box-filters one cubemap mip level into the next
+@compute @workgroup_size(8, 8, 1)
+fn downsample_cube(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.size || id.y >= params.size) {
+        return;
+    }
+    let src = vec2<i32>(id.xy * 2u);
+    let layer = i32(id.z);
+    let color = textureLoad(src_level, src, layer, 0)
+        + textureLoad(src_level, src + vec2<i32>(1, 0), layer, 0)
+        + textureLoad(src_level, src + vec2<i32>(0, 1), layer, 0)
+        + textureLoad(src_level, src + vec2<i32>(1, 1), layer, 0);
+    textureStore(dst_cube, id.xy, id.z, vec4<f32>(color.rgb / 4.0, 1.0));
+}
+
+// Diffuse irradiance, as a cosine-weighted integral over the hemisphere
+@compute @workgroup_size(8, 8, 1)
+fn convolve_irradiance(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.size || id.y >= params.size) {
+        return;
+    }
+    let n = cube_dir(id, params.size);
+    var up = vec3<f32>(0.0, 1.0, 0.0);
+    if (abs(n.y) > 0.999) {
+        up = vec3<f32>(0.0, 0.0, 1.0);
+    }
+    let right = normalize(cross(up, n));
+    up = cross(n, right);
+
+    // Sample a low mip, since the result is very blurry anyways
+    let lod = max(f32(params.src_mips) - 6.0, 0.0);
+    let step = 0.05;
+    var total = vec3<f32>(0.0);
+    var count = 0.0;
+    for (var phi = 0.0; phi < 2.0 * PI; phi += step) {
+        for (var theta = 0.0; theta < 0.5 * PI; theta += step) {
+            let t = vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            let dir = t.x * right + t.y * up + t.z * n;
+            total += textureSampleLevel(src_cube, src_sampler, dir, lod).rgb
+                * cos(theta) * sin(theta);
+            count += 1.0;
+        }
+    }
+    textureStore(dst_cube, id.xy, id.z, vec4<f32>(PI * total / count, 1.0));
+}
+
+// Specular radiance for one roughness level, using filtered importance
+// sampling of the GGX lobe (with N = V = R)
+@compute @workgroup_size(8, 8, 1)
+fn prefilter_specular(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.size || id.y >= params.size) {
+        return;
+    }
+    let n = cube_dir(id, params.size);
+    let roughness = params.roughness;
+    if (roughness == 0.0) {
+        let color = textureSampleLevel(src_cube, src_sampler, n, 0.0);
+        textureStore(dst_cube, id.xy, id.z, vec4<f32>(color.rgb, 1.0));
+        return;
+    }
+
+    let sample_count = 256u;
+    let texel_solid_angle = 4.0 * PI / (6.0 * f32(params.src_size * params.src_size));
+    var total = vec3<f32>(0.0);
+    var weight = 0.0;
+    for (var i = 0u; i < sample_count; i++) {
+        let h = importance_sample_ggx(hammersley(i, sample_count), n, roughness);
+        let l = normalize(2.0 * dot(n, h) * h - n);
+        let n_dot_l = dot(n, l);
+        if (n_dot_l > 0.0) {
+            let n_dot_h = max(dot(n, h), 0.0);
+            let pdf = distribution_ggx(n_dot_h, roughness) / 4.0 + 0.0001;
+            let sample_solid_angle = 1.0 / (f32(sample_count) * pdf);
+            let lod = clamp(
+                0.5 * log2(sample_solid_angle / texel_solid_angle) + 1.0,
+                0.0,
+                f32(params.src_mips - 1u),
+            );
+            total += textureSampleLevel(src_cube, src_sampler, l, lod).rgb * n_dot_l;
+            weight += n_dot_l;
+        }
+    }
+    textureStore(dst_cube, id.xy, id.z, vec4<f32>(total / weight, 1.0));
+}
+
+// Split-sum BRDF lookup table, indexed by (N.V, roughness)
+@compute @workgroup_size(8, 8, 1)
+fn integrate_brdf(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.size || id.y >= params.size) {
+        return;
+    }
+    let n_dot_v = (f32(id.x) + 0.5) / f32(params.size);
+    let roughness = (f32(id.y) + 0.5) / f32(params.size);
+    let v = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    let n = vec3<f32>(0.0, 0.0, 1.0);
+
+    let sample_count = 512u;
+    var a = 0.0;
+    var b = 0.0;
+    for (var i = 0u; i < sample_count; i++) {
+        let h = importance_sample_ggx(hammersley(i, sample_count), n, roughness);
+        let l = normalize(2.0 * dot(v, h) * h - v);
+        let n_dot_l = max(l.z, 0.0);
+        let n_dot_h = max(h.z, 0.0);
+        let v_dot_h = max(dot(v, h), 0.0);
+        if (n_dot_l > 0.0) {
+            let g = geometry_smith_ibl(n_dot_v, n_dot_l, roughness);
+            let g_vis = g * v_dot_h / (n_dot_h * n_dot_v);
+            let fc = pow(1.0 - v_dot_h, 5.0);
+            a += (1.0 - fc) * g_vis;
+            b += fc * g_vis;
+        }
+    }
+    let scale = vec2<f32>(a, b) / f32(sample_count);
+    textureStore(dst_lut, id.xy, vec4<f32>(scale, 0.0, 1.0));
+}
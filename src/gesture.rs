@@ -0,0 +1,96 @@
+use nalgebra_glm::Vec2;
+
+use glb_to_webgpu::{winit::event::TouchPhase, Camera};
+
+/// Maximum finger travel, in pixels, for a touch to count as a tap
+const TAP_DISTANCE: f32 = 10.0;
+
+struct Point {
+    id: u64,
+    start: Vec2,
+    pos: Vec2,
+}
+
+/// Tracks active touches and turns them into camera movement.  One finger
+/// rotates (orbiting, or looking around in fly mode), while two fingers pan,
+/// pinch to zoom and twist to turn.
+#[derive(Default)]
+pub struct Touches {
+    points: Vec<Point>,
+    /// Set while the current gesture could still be a tap, i.e. a single
+    /// finger which hasn't moved far
+    tap: bool,
+}
+
+impl Touches {
+    /// Handles a touch event, returning the position of a tap if this ended
+    /// one
+    pub fn update(
+        &mut self,
+        camera: &mut Camera,
+        id: u64,
+        phase: TouchPhase,
+        pos: Vec2,
+    ) -> Option<Vec2> {
+        match phase {
+            TouchPhase::Started => {
+                self.points.push(Point { id, start: pos, pos });
+                self.tap = self.points.len() == 1;
+                camera.set_touch_drag(true);
+            }
+            TouchPhase::Moved => {
+                let before = self.pair();
+                let point = self.points.iter_mut().find(|p| p.id == id)?;
+                let from = std::mem::replace(&mut point.pos, pos);
+                if (pos - point.start).norm() > TAP_DISTANCE {
+                    self.tap = false;
+                }
+                match (before, self.pair()) {
+                    (Some(before), Some(after)) => Self::two_finger(camera, before, after),
+                    _ if self.points.len() == 1 => camera.touch_rotate(from, pos),
+                    _ => (),
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let tap = phase == TouchPhase::Ended && self.tap && self.points.len() == 1;
+                self.points.retain(|p| p.id != id);
+                // Lifting one finger of several shouldn't leave a tap behind
+                self.tap = false;
+                if self.points.is_empty() {
+                    camera.set_touch_drag(false);
+                }
+                if tap {
+                    return Some(pos);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the positions of the first two touches, if there are two
+    fn pair(&self) -> Option<(Vec2, Vec2)> {
+        match self.points.as_slice() {
+            [a, b, ..] => Some((a.pos, b.pos)),
+            _ => None,
+        }
+    }
+
+    fn two_finger(camera: &mut Camera, before: (Vec2, Vec2), after: (Vec2, Vec2)) {
+        let center_before = (before.0 + before.1) / 2.0;
+        let center_after = (after.0 + after.1) / 2.0;
+        camera.touch_pan(center_before, center_after);
+
+        // Fingers which are (nearly) on top of each other give no direction
+        let span_before = before.1 - before.0;
+        let span_after = after.1 - after.0;
+        if span_before.norm() < 1.0 || span_after.norm() < 1.0 {
+            return;
+        }
+        camera.touch_zoom(span_after.norm() / span_before.norm(), center_after);
+        let angle = span_after.y.atan2(span_after.x) - span_before.y.atan2(span_before.x);
+        // Wrap into [-pi, pi], in case the fingers cross the atan2 seam
+        let angle = (angle + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        camera.twist(angle);
+    }
+}
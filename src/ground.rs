@@ -0,0 +1,532 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra_glm as glm;
+use wgpu::util::DeviceExt;
+
+use crate::winit::dpi::PhysicalSize;
+use crate::{
+    bounds::Aabb,
+    camera::Camera,
+    model::{GPUVertex, Model},
+    renderer::DEPTH_FORMAT,
+    shadow::Shadow,
+    texture::Texture,
+};
+
+/// Resolution of the baked contact shadow
+const CONTACT_SIZE: u32 = 256;
+const CONTACT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+/// What the ground plane shows beneath the model
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GroundStyle {
+    /// No ground plane
+    Off = 0,
+    /// Receives shadows from the key light
+    Shadow = 1,
+    /// A soft, blurred contact shadow which doesn't depend on the light
+    Contact = 2,
+}
+
+impl FromStr for GroundStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(GroundStyle::Off),
+            "shadow" => Ok(GroundStyle::Shadow),
+            "contact" => Ok(GroundStyle::Contact),
+            _ => Err(format!(
+                "Invalid ground '{}', expected off, shadow or contact",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GroundOptions {
+    pub style: GroundStyle,
+    /// Whether the ground also shows a planar reflection of the model
+    pub reflection: bool,
+}
+
+/// Uniforms for `ground.wgsl`, which must match `Locals` in the shader
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Locals {
+    view_mat: [[f32; 4]; 4],
+    model_mat: [[f32; 4]; 4],
+    /// Ground center (xyz) and half-extent (w)
+    plane: [f32; 4],
+    mode: u32,
+    reflection: u32,
+    /// Height above the ground at which contact shadows fade out
+    falloff: f32,
+    _padding: u32,
+}
+
+/// A shadow-catching ground plane placed at the bottom of the model's
+/// bounding box, with an optional planar reflection
+pub struct Ground {
+    options: GroundOptions,
+    locals: Locals,
+    format: wgpu::TextureFormat,
+
+    uniform_buf: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    sampler: wgpu::Sampler,
+    contact: Option<wgpu::TextureView>,
+    /// Color and depth targets for the mirrored model, if reflections are on
+    reflection_target: Option<(wgpu::TextureView, wgpu::TextureView)>,
+    /// Bound in place of disabled textures
+    placeholder: Texture,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Ground {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        shadow: &Shadow,
+        bounds: &Aabb,
+        options: GroundOptions,
+    ) -> Self {
+        let center = bounds.center();
+        let locals = Locals {
+            view_mat: glm::Mat4::identity().into(),
+            model_mat: glm::Mat4::identity().into(),
+            plane: [center.x, bounds.min.y, center.z, bounds.radius() * 2.0],
+            mode: options.style as u32,
+            reflection: options.reflection as u32,
+            falloff: (bounds.size().y * 0.5).max(1e-6),
+            _padding: 0,
+        };
+
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Ground Uniform Buffer"),
+            contents: bytemuck::bytes_of(&locals),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = Self::shader(device);
+        let placeholder = Texture::solid(device, queue, [0, 0, 0, 0]);
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ground"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<Locals>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                texture_entry(1),
+                texture_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout, shadow.bind_group_layout()],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ground"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Greater,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ground"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&placeholder.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&placeholder.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Ground {
+            options,
+            locals,
+            format,
+            uniform_buf,
+            bind_group_layout,
+            bind_group,
+            sampler,
+            contact: None,
+            reflection_target: None,
+            placeholder,
+            render_pipeline,
+        }
+    }
+
+    /// Returns the height of the ground plane, if it's drawn with a
+    /// reflection of the model
+    pub fn mirror_height(&self) -> Option<f32> {
+        (self.options.style != GroundStyle::Off && self.options.reflection)
+            .then_some(self.locals.plane[1])
+    }
+
+    /// Returns the color and depth views into which the mirrored model should
+    /// be drawn, if reflections are enabled
+    pub fn reflection_target(&self) -> Option<(&wgpu::TextureView, &wgpu::TextureView)> {
+        self.reflection_target.as_ref().map(|(c, d)| (c, d))
+    }
+
+    /// Rebuilds the reflection targets to match the window size
+    pub fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        if self.mirror_height().is_some() {
+            self.reflection_target =
+                Some(Self::create_reflection_target(device, self.format, size));
+            self.rebind(device);
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        camera: &Camera,
+        queue: &wgpu::Queue,
+        shadow: &Shadow,
+        view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        if self.options.style == GroundStyle::Off {
+            return;
+        }
+        self.locals.view_mat = camera.view_matrix().into();
+        self.locals.model_mat = camera.model_matrix().into();
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&self.locals));
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("ground"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_bind_group(1, shadow.bind_group(), &[]);
+        rpass.draw(0..6, 0..1);
+    }
+
+    fn shader(device: &wgpu::Device) -> wgpu::ShaderModule {
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("ground.wgsl"))),
+        })
+    }
+
+    /// Rebuilds the bind group after the contact or reflection textures
+    /// change, binding placeholders for whichever are disabled
+    fn rebind(&mut self, device: &wgpu::Device) {
+        let contact = self.contact.as_ref().unwrap_or(&self.placeholder.view);
+        let reflection = self
+            .reflection_target
+            .as_ref()
+            .map(|(color, _)| color)
+            .unwrap_or(&self.placeholder.view);
+        self.bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ground"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(contact),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(reflection),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+    }
+
+    fn create_reflection_target(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+    ) -> (wgpu::TextureView, wgpu::TextureView) {
+        let size = wgpu::Extent3d {
+            width: size.width.max(1),
+            height: size.height.max(1),
+            depth_or_array_layers: 1,
+        };
+        let texture = |label, format, usage| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage,
+                    view_formats: &[],
+                })
+                .create_view(&Default::default())
+        };
+        (
+            texture(
+                "reflection",
+                format,
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            ),
+            texture(
+                "reflection depth",
+                DEPTH_FORMAT,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            ),
+        )
+    }
+
+    /// Renders the model's height above the ground from below, then blurs
+    /// it into a soft contact shadow.  The model is static, so this only
+    /// needs to happen once (and only in contact shadow mode).
+    pub fn bake_contact(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, model: &Model) {
+        if self.options.style != GroundStyle::Contact {
+            return;
+        }
+        let shader = &Self::shader(device);
+        let uniform_buf = &self.uniform_buf;
+        let sampler = &self.sampler;
+
+        let texture = || {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("contact shadow"),
+                    size: wgpu::Extent3d {
+                        width: CONTACT_SIZE,
+                        height: CONTACT_SIZE,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: CONTACT_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&Default::default())
+        };
+        let (a, b) = (texture(), texture());
+
+        // Keep the closest surface to the ground at each texel
+        let contact_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("contact shadow"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_contact",
+                buffers: &[GPUVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_contact",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: CONTACT_FORMAT,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::One,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Max,
+                        },
+                        alpha: wgpu::BlendComponent::REPLACE,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let contact_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &contact_pipeline.get_bind_group_layout(0),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            }],
+        });
+
+        let blur_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("contact blur"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_blur",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_blur",
+                targets: &[Some(CONTACT_FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let blur_bind_group = |src: &wgpu::TextureView, step: [f32; 2]| {
+            let buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&[step[0], step[1], 0.0, 0.0]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &blur_pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::TextureView(src),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: buf.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let texel = 2.0 / CONTACT_SIZE as f32;
+        let horizontal = blur_bind_group(&a, [texel, 0.0]);
+        let vertical = blur_bind_group(&b, [0.0, texel]);
+
+        let pass = |encoder: &mut wgpu::CommandEncoder,
+                    target: &wgpu::TextureView,
+                    pipeline: &wgpu::RenderPipeline,
+                    bind_group: &wgpu::BindGroup,
+                    geometry: bool| {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("contact shadow"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, bind_group, &[]);
+            if geometry {
+                model.draw_geometry(&mut rpass);
+            } else {
+                rpass.draw(0..6, 0..1);
+            }
+        };
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        pass(&mut encoder, &a, &contact_pipeline, &contact_bind_group, true);
+        pass(&mut encoder, &b, &blur_pipeline, &horizontal, false);
+        pass(&mut encoder, &a, &blur_pipeline, &vertical, false);
+        queue.submit(Some(encoder.finish()));
+
+        self.contact = Some(a);
+        self.rebind(device);
+    }
+}
@@ -0,0 +1,190 @@
+struct Locals {
+    view_mat: mat4x4<f32>,
+    model_mat: mat4x4<f32>,
+    // Ground center (xyz) and half-extent (w)
+    plane: vec4<f32>,
+    mode: u32,
+    reflection: u32,
+    // Height above the ground at which contact shadows fade out
+    falloff: f32,
+};
+@group(0) @binding(0)
+var<uniform> r_locals: Locals;
+@group(0) @binding(1)
+var r_contact: texture_2d<f32>;
+@group(0) @binding(2)
+var r_reflection: texture_2d<f32>;
+@group(0) @binding(3)
+var r_sampler: sampler;
+
+struct BlurParams {
+    // Offset between taps, in UV units
+    step: vec2<f32>,
+};
+@group(0) @binding(4)
+var r_blur_src: texture_2d<f32>;
+@group(0) @binding(5)
+var<uniform> r_blur: BlurParams;
+
+struct Light {
+    view_proj: mat4x4<f32>,
+    direction: vec4<f32>,
+    color: vec4<f32>,
+    shadows: u32,
+};
+@group(1) @binding(0)
+var<uniform> r_light: Light;
+@group(1) @binding(1)
+var r_shadow_map: texture_depth_2d;
+@group(1) @binding(2)
+var r_shadow_sampler: sampler_comparison;
+
+// Must match GroundStyle in ground.rs
+const MODE_SHADOW: u32 = 1u;
+const MODE_CONTACT: u32 = 2u;
+
+// Must match SHADOW_SIZE in shadow.rs
+const SHADOW_SIZE: f32 = 2048.0;
+
+// Returns one of the six corners of a [-1, 1] square, for two triangles
+fn quad_corner(i: u32) -> vec2<f32> {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+    );
+    return corners[i];
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Ground plane
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_pos: vec4<f32>,
+    // Position on the plane, from -1 to 1 on each axis
+    @location(1) local: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let c = quad_corner(in_vertex_index);
+    let p = r_locals.plane;
+    out.world_pos = vec4<f32>(p.x + c.x * p.w, p.y, p.z + c.y * p.w, 1.0);
+    out.position = r_locals.view_mat * r_locals.model_mat * out.world_pos;
+    out.local = c;
+    return out;
+}
+
+fn shadow_factor(world_pos: vec4<f32>) -> f32 {
+    if (r_light.shadows == 0u) {
+        return 1.0;
+    }
+    let clip = r_light.view_proj * world_pos;
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    // Outside the light's frustum, so nothing can be in the way
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0) {
+        return 1.0;
+    }
+    var total = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) / SHADOW_SIZE;
+            total += textureSampleCompareLevel(r_shadow_map, r_shadow_sampler, uv + offset, ndc.z);
+        }
+    }
+    return total / 9.0;
+}
+
+// The ground is a shadow catcher: it only darkens (and optionally reflects)
+// whatever backdrop is behind it, fading out towards its edges.  Output is
+// premultiplied alpha.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let fade = 1.0 - smoothstep(0.4, 1.0, length(in.local));
+
+    var shadow = 0.0;
+    if (r_locals.mode == MODE_SHADOW) {
+        shadow = 0.6 * (1.0 - shadow_factor(in.world_pos));
+    } else if (r_locals.mode == MODE_CONTACT) {
+        let uv = in.local * 0.5 + 0.5;
+        shadow = 0.8 * textureSampleLevel(r_contact, r_sampler, uv, 0.0).r;
+    }
+    shadow *= fade;
+
+    var color = vec3<f32>(0.0);
+    var alpha = shadow;
+    if (r_locals.reflection != 0u) {
+        let uv = in.position.xy / vec2<f32>(textureDimensions(r_reflection));
+        let refl = textureSampleLevel(r_reflection, r_sampler, uv, 0.0);
+        let k = 0.3 * refl.a * fade * (1.0 - shadow);
+        color = refl.rgb * k;
+        alpha = 1.0 - (1.0 - shadow) * (1.0 - k);
+    }
+    // Invisible ground mustn't write depth, or SSAO and the overlays would
+    // treat it as solid
+    if (alpha < 1.0 / 255.0) {
+        discard;
+    }
+    return vec4<f32>(color, alpha);
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Contact shadows, rendered once from below the model
+
+struct ContactOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) height: f32,
+};
+
+@vertex
+fn vs_contact(@location(0) position: vec4<f32>) -> ContactOutput {
+    var out: ContactOutput;
+    let p = r_locals.plane;
+    out.position = vec4<f32>(
+        (position.x - p.x) / p.w,
+        -(position.z - p.z) / p.w,
+        0.5,
+        1.0,
+    );
+    out.height = position.y - p.y;
+    return out;
+}
+
+@fragment
+fn fs_contact(in: ContactOutput) -> @location(0) vec4<f32> {
+    let occlusion = 1.0 - clamp(in.height / r_locals.falloff, 0.0, 1.0);
+    return vec4<f32>(occlusion * occlusion, 0.0, 0.0, 1.0);
+}
+
+struct BlurOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_blur(@builtin(vertex_index) in_vertex_index: u32) -> BlurOutput {
+    var out: BlurOutput;
+    let c = quad_corner(in_vertex_index);
+    out.position = vec4<f32>(c, 0.0, 1.0);
+    out.uv = c * vec2<f32>(0.5, -0.5) + 0.5;
+    return out;
+}
+
+// Separable 9-tap Gaussian blur
+@fragment
+fn fs_blur(in: BlurOutput) -> @location(0) vec4<f32> {
+    var weights = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+    var total = textureSampleLevel(r_blur_src, r_sampler, in.uv, 0.0).r * weights[0];
+    for (var i = 1; i < 5; i++) {
+        let offset = r_blur.step * f32(i);
+        total += textureSampleLevel(r_blur_src, r_sampler, in.uv + offset, 0.0).r * weights[i];
+        total += textureSampleLevel(r_blur_src, r_sampler, in.uv - offset, 0.0).r * weights[i];
+    }
+    return vec4<f32>(total, 0.0, 0.0, 1.0);
+}
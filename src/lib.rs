@@ -0,0 +1,54 @@
+//! Renders glTF models with wgpu.
+//!
+//! Load a scene with [`gltf::Gltf`], create a [`Renderer`] for it on your own
+//! device, then draw it each frame into any texture view (a [`Target`]), as
+//! seen through a [`Camera`].  The `glb-to-webgpu` viewer binary is a thin
+//! windowed app built on this.
+
+pub(crate) mod backdrop;
+pub(crate) mod bookmarks;
+pub(crate) mod bounds;
+pub(crate) mod bvh;
+pub(crate) mod camera;
+pub(crate) mod environment;
+pub(crate) mod ground;
+pub(crate) mod model;
+pub(crate) mod outline;
+pub(crate) mod overlay;
+pub(crate) mod post;
+pub(crate) mod renderer;
+pub(crate) mod shadow;
+pub(crate) mod texture;
+pub(crate) mod time;
+pub(crate) mod tonemap;
+#[cfg(not(target_arch="wasm32"))]
+pub use winit;
+#[cfg(target_arch="wasm32")]
+pub use winit_compat as winit;
+#[cfg(any(test, target_arch="wasm32"))]
+pub mod winit_compat;
+
+#[cfg(not(target_arch="wasm32"))]
+pub extern crate wgpu_native as wgpu;
+#[cfg(target_arch="wasm32")]
+pub extern crate wgpu_wasi as wgpu;
+
+pub use gltf;
+
+pub use backdrop::Backdrop;
+pub use camera::Camera;
+pub use model::Model;
+pub use renderer::{RenderOptions, Renderer, Target};
+
+// Types which the API above takes or returns
+pub use backdrop::BackdropStyle;
+pub use bookmarks::{Bookmark, Bookmarks};
+pub use bounds::Aabb;
+pub use camera::{CameraMode, PresetView, Projection};
+pub use ground::{GroundOptions, GroundStyle};
+pub use model::{Pick, Primitive, SceneCamera, ViewMode};
+pub use overlay::OverlayKind;
+pub use post::EffectKind;
+pub use texture::{Image, Texture};
+pub use time::Instant;
+pub use tonemap::ToneMapping;
@@ -1,122 +1,73 @@
-use gltf::Gltf;
-use nalgebra_glm::Vec2;
-use std::sync::Arc;
-use crate::winit::{
-    event::{ElementState, Event, MouseScrollDelta, WindowEvent},
-    event_loop::EventLoop,
-    window::Window,
-};
+use glb_to_webgpu::gltf::{self, Gltf};
+use glb_to_webgpu::winit::event_loop::EventLoop;
 
+pub(crate) mod adapter;
 pub(crate) mod app;
-pub(crate) mod backdrop;
-pub(crate) mod camera;
-pub(crate) mod model;
-#[cfg(not(target_arch="wasm32"))]
-pub(crate) use winit;
+pub(crate) mod bindings;
+pub(crate) mod gesture;
+pub(crate) mod options;
+pub(crate) mod schedule;
+pub(crate) mod screenshot;
+pub(crate) mod viewer;
 #[cfg(target_arch="wasm32")]
-pub(crate) mod winit;
+pub(crate) mod wasi_cli;
 
-#[cfg(not(target_arch="wasm32"))]
-extern crate wgpu_native as wgpu;
-#[cfg(target_arch="wasm32")]
-extern crate wgpu_wasi as wgpu;
-
-use crate::app::App;
+use crate::{options::Options, viewer::Viewer};
 
-async fn run(event_loop: EventLoop<()>, window: Arc<Window>, gltf: Gltf) {
-    let size = window.inner_size();
-    let (surface, adapter) = {
-        let instance = wgpu::Instance::new(Default::default());
-        let surface = instance.create_surface(Arc::clone(&window)).unwrap();
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                // Request an adapter which can render to our surface
-                compatible_surface: Some(&surface),
-                ..Default::default()
-            })
-            .await
-            .expect("Failed to find an appropriate adapter");
-        (surface, adapter)
+/// Loads the model named in the options, or the built-in axis model if none
+/// was given
+fn load_model(options: &Options) -> Result<Gltf, String> {
+    let Some(path) = &options.model else {
+        return Ok(gltf::Gltf::from_slice(include_bytes!("../axis.glb")).unwrap());
     };
+    #[cfg(not(target_arch="wasm32"))]
+    let data = std::fs::read(path).map_err(|e| e.to_string());
+    #[cfg(target_arch="wasm32")]
+    let data = wasi_cli::read(path);
+    let data = data.map_err(|e| format!("Failed to read model {:?}: {}", path, e))?;
+    gltf::Gltf::from_slice(&data).map_err(|e| format!("Failed to load model {:?}: {}", path, e))
+}
 
-    // Create the logical device and command queue
-    let (device, queue) = adapter
-        .request_device(&Default::default(), None)
-        .await
-        .expect("Failed to create device");
-
-    surface.configure(
-        &device,
-        &surface
-            .get_default_config(&adapter, size.width, size.height)
-            .unwrap(),
-    );
+/// Opens the viewer window, or lists the adapters, as the options ask
+fn start(options: Options) -> Result<(), String> {
+    if options.list_adapters {
+        adapter::list(&options.gpu);
+        return Ok(());
+    }
+    let gltf = load_model(&options)?;
 
-    let mut app = App::new(size, adapter, surface, device, gltf);
+    let event_loop = EventLoop::new().unwrap();
+    let mut viewer = Viewer::new(gltf, options);
+    event_loop.run_app(&mut viewer).unwrap();
+    viewer.take_error().map_or(Ok(()), Err)
+}
 
-    event_loop
-        .run(move |event, event_loop| match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => {
-                    event_loop.exit();
-                }
-                WindowEvent::RedrawRequested => {
-                    app.redraw(&queue);
-                }
-                WindowEvent::Resized(size) => {
-                    app.resize(size);
-                    window.request_redraw();
-                }
-                WindowEvent::MouseInput { button, state, .. } => {
-                    match state {
-                        ElementState::Pressed => app.camera.mouse_pressed(button),
-                        ElementState::Released => app.camera.mouse_released(button),
-                    }
-                    window.request_redraw();
-                }
-                WindowEvent::CursorMoved { position, .. } => {
-                    app.camera
-                        .mouse_move(Vec2::new(position.x as f32, position.y as f32));
-                    window.request_redraw();
-                }
-                WindowEvent::MouseWheel { delta, .. } => {
-                    if let MouseScrollDelta::LineDelta(_, verti) = delta {
-                        app.camera.mouse_scroll(verti * 10.0);
-                    }
-                    window.request_redraw();
-                }
-                _ => {}
-            },
-            Event::DeviceEvent { event, .. } => app.device_event(event),
-            _ => (),
-        })
-        .unwrap();
+fn init_logger(filters: Option<String>) {
+    let mut builder = env_logger::builder();
+    match filters {
+        Some(filters) => builder.parse_filters(&filters),
+        None => builder.filter_level(log::LevelFilter::Info),
+    };
+    builder.init();
 }
 
 fn main() {
-    env_logger::builder()
-        .filter_level(log::LevelFilter::Info)
-        .init();
-
-    let gltf = gltf::Gltf::from_slice(include_bytes!("../axis.glb")).unwrap();
-
-    let event_loop = EventLoop::<()>::new().unwrap();
-    #[cfg(not(target_arch="wasm32"))]
-    let window = event_loop.create_window(Default::default()).unwrap();
-    #[cfg(target_arch="wasm32")]
-    let window = winit::window::WindowBuilder::new().build(&event_loop).unwrap();
-    let window = Arc::new(window);
-    pollster::block_on(run(event_loop, window, gltf));
+    init_logger(std::env::var("RUST_LOG").ok());
+    if let Err(e) = start(Options::from_args()) {
+        log::error!("{}", e);
+        std::process::exit(1);
+    }
 }
 
 #[cfg(target_arch="wasm32")]
 struct MyCliRunner;
 #[cfg(target_arch="wasm32")]
 impl ::wasi::exports::cli::run::Guest for MyCliRunner {
+    /// Takes the command line and environment from the host, rather than
+    /// from `std`, so the component sees what `wasmtime run` etc. passed it
     fn run() -> Result<(), ()> {
-        main();
-        Ok(())
+        init_logger(wasi_cli::var("RUST_LOG"));
+        start(Options::parse_from(wasi_cli::args())).map_err(|e| log::error!("{}", e))
     }
 }
 #[cfg(target_arch="wasm32")]
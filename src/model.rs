@@ -1,12 +1,19 @@
 use std::borrow::Cow;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::str::FromStr;
 
 use bytemuck::{Pod, Zeroable};
-use glm::{Mat4, Vec4};
+use glm::{Mat3, Mat4, Vec3, Vec4};
 use gltf::Gltf;
+use log::warn;
 use nalgebra_glm as glm;
 use wgpu::util::DeviceExt;
 
-use crate::camera::Camera;
+use crate::{
+    bounds::Aabb, bvh::Bvh, camera::Camera, environment::Environment, renderer::DEPTH_FORMAT,
+    shadow::Shadow,
+};
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
@@ -14,23 +21,398 @@ pub struct GPUVertex {
     pub pos: [f32; 4],
     pub norm: [f32; 4],
     pub color: [f32; 4],
+    pub uv: [f32; 2],
 }
 
+impl GPUVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 4] = [
+        // Positions
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: 0,
+            shader_location: 0,
+        },
+        // Normals
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: std::mem::size_of::<Vec4>() as wgpu::BufferAddress,
+            shader_location: 1,
+        },
+        // Colors
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: 2 * std::mem::size_of::<Vec4>() as wgpu::BufferAddress,
+            shader_location: 2,
+        },
+        // Texture coordinates
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x2,
+            offset: 3 * std::mem::size_of::<Vec4>() as wgpu::BufferAddress,
+            shader_location: 3,
+        },
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GPUVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+
+    /// Layout which steps once per instance, for drawing a small piece of
+    /// geometry at every vertex (e.g. normal lines)
+    fn instance_desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            step_mode: wgpu::VertexStepMode::Instance,
+            ..Self::desc()
+        }
+    }
+}
+
+/// How the model is shaded, for inspecting assets
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ViewMode {
+    /// Full PBR shading
+    Shaded = 0,
+    /// Shaded, with triangle edges drawn on top
+    Wireframe = 1,
+    /// Shaded, with a line along each vertex normal
+    Normals = 2,
+    /// World-space normals as colors
+    WorldNormals = 3,
+    /// A checkerboard in texture space, to show UV layout and stretching
+    UvChecker = 4,
+    /// Vertex colors only
+    VertexColor = 5,
+    /// A random color for each primitive
+    Primitives = 6,
+}
+
+impl ViewMode {
+    const ALL: [ViewMode; 7] = [
+        ViewMode::Shaded,
+        ViewMode::Wireframe,
+        ViewMode::Normals,
+        ViewMode::WorldNormals,
+        ViewMode::UvChecker,
+        ViewMode::VertexColor,
+        ViewMode::Primitives,
+    ];
+
+    /// Returns the next mode, wrapping around after the last one
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+impl FromStr for ViewMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shaded" => Ok(ViewMode::Shaded),
+            "wireframe" => Ok(ViewMode::Wireframe),
+            "normals" => Ok(ViewMode::Normals),
+            "world-normals" => Ok(ViewMode::WorldNormals),
+            "uv" => Ok(ViewMode::UvChecker),
+            "vertex-color" => Ok(ViewMode::VertexColor),
+            "primitives" => Ok(ViewMode::Primitives),
+            _ => Err(format!(
+                "Invalid view mode '{}', expected shaded, wireframe, normals, \
+                 world-normals, uv, vertex-color or primitives",
+                s
+            )),
+        }
+    }
+}
+
+/// Per-frame uniforms, which must match `Locals` in `model.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Locals {
+    view_mat: [[f32; 4]; 4],
+    model_mat: [[f32; 4]; 4],
+    /// World-space direction towards the viewer (xyz).  `w` is -1 when
+    /// drawing mirrored geometry, which flips the triangle winding.
+    eye: [f32; 4],
+    /// One of `ViewMode`
+    mode: u32,
+    /// World-space length of the lines drawn in `ViewMode::Normals`
+    normal_length: f32,
+    /// Index of the highlighted primitive plus one, or 0 if none is
+    selected: u32,
+    _padding: u32,
+}
+
+/// Material factors, which must match `Material` in `model.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct GPUMaterial {
+    base_color: [f32; 4],
+    emissive: [f32; 4],
+    metallic: f32,
+    roughness: f32,
+    _padding: [f32; 2],
+}
+
+impl Default for GPUMaterial {
+    /// The glTF default material
+    fn default() -> Self {
+        GPUMaterial {
+            base_color: [1.0; 4],
+            emissive: [0.0, 0.0, 0.0, 1.0],
+            metallic: 1.0,
+            roughness: 1.0,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+impl From<gltf::Material<'_>> for GPUMaterial {
+    fn from(m: gltf::Material) -> Self {
+        let pbr = m.pbr_metallic_roughness();
+        // Emissive strength pushes emissive surfaces above 1.0, which is
+        // what drives bloom
+        let e = m.emissive_factor();
+        let strength = m.emissive_strength().unwrap_or(1.0);
+        GPUMaterial {
+            base_color: pbr.base_color_factor(),
+            emissive: [e[0] * strength, e[1] * strength, e[2] * strength, 1.0],
+            metallic: pbr.metallic_factor(),
+            roughness: pbr.roughness_factor(),
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+/// A single glTF primitive, stored as a range within the model's shared
+/// vertex and index buffers.  Vertices are pre-transformed into world space.
+#[derive(Clone, Debug)]
+pub struct Primitive {
+    /// Index of the glTF node which instanced this primitive, or `None` if
+    /// the file has no scene and meshes are drawn in place
+    pub node: Option<usize>,
+    /// Index of the glTF mesh, and of this primitive within it
+    pub mesh: usize,
+    pub index: usize,
+    /// Index of the glTF material, or `None` for the default material
+    pub material: Option<usize>,
+    pub indices: Range<u32>,
+}
+
+/// A camera placed in the glTF scene
+#[derive(Clone, Debug)]
+pub struct SceneCamera {
+    pub name: String,
+    /// World-space transform of the camera's node, which looks down -Z
+    pub transform: Mat4,
+    /// Half-width of the view for orthographic cameras, or `None` for
+    /// perspective cameras
+    pub xmag: Option<f32>,
+}
+
+/// The result of picking a point on the model
+#[derive(Copy, Clone, Debug)]
+pub struct Pick {
+    /// Index into `Model::primitives`
+    pub primitive: usize,
+    /// Index of the triangle within its primitive
+    pub triangle: u32,
+    /// World-space position of the hit
+    pub position: Vec3,
+}
+
+/// A glTF model, flattened into one set of buffers.  The CPU-side geometry
+/// and materials are kept alongside the GPU resources, so that the latter can
+/// be rebuilt (e.g. on a new device, after the old one was lost).
 pub struct Model {
+    gpu: Gpu,
+    vertices: Vec<GPUVertex>,
+    indices: Vec<u32>,
+    /// Material 0 is the default material, followed by the glTF materials
+    materials: Vec<GPUMaterial>,
+    primitives: Vec<Primitive>,
+    node_bounds: Vec<Aabb>,
+    cameras: Vec<SceneCamera>,
+    bvh: Bvh,
+    /// Index of the highlighted primitive, if any
+    selected: Option<usize>,
+
+    view_mode: ViewMode,
+    normal_length: f32,
+}
+
+/// Everything the model keeps on the GPU
+struct Gpu {
     vertex_buf: wgpu::Buffer,
     index_buf: wgpu::Buffer,
     uniform_buf: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
-    index_count: u32,
+    /// Uniforms for drawing the model mirrored below the ground plane
+    mirror_uniform_buf: wgpu::Buffer,
+    mirror_bind_group: wgpu::BindGroup,
+    material_bind_group: wgpu::BindGroup,
+    /// Distance between materials in the material buffer, which is padded to
+    /// satisfy the device's dynamic offset alignment
+    material_stride: wgpu::BufferAddress,
     render_pipeline: wgpu::RenderPipeline,
+
+    /// Each triangle edge, as a line list, for `ViewMode::Wireframe`
+    wire_index_buf: wgpu::Buffer,
+    wire_index_count: u32,
+    wire_pipeline: wgpu::RenderPipeline,
+    normal_pipeline: wgpu::RenderPipeline,
+}
+
+/// CPU-side geometry, accumulated while walking the scene graph
+#[derive(Default)]
+struct Geometry {
+    vertices: Vec<GPUVertex>,
+    indices: Vec<u32>,
+    primitives: Vec<Primitive>,
+    /// Bounds of each node with a mesh, in world space
+    node_bounds: Vec<Aabb>,
+    cameras: Vec<SceneCamera>,
+}
+
+impl Geometry {
+    fn add_mesh(
+        &mut self,
+        mesh: gltf::Mesh,
+        node: Option<usize>,
+        transform: &Mat4,
+        buffer_data: &[Vec<u8>],
+    ) {
+        let normal_mat: Mat3 = glm::mat4_to_mat3(transform)
+            .try_inverse()
+            .unwrap_or_else(Mat3::identity)
+            .transpose();
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                warn!(
+                    "Skipping primitive {} of mesh {}: unsupported mode {:?}",
+                    primitive.index(),
+                    mesh.index(),
+                    primitive.mode()
+                );
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&buffer_data[buffer.index()]));
+
+            let positions = match reader.read_positions() {
+                Some(p) => p.collect::<Vec<_>>(),
+                None => continue,
+            };
+            let first_vertex = self.vertices.len() as u32;
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect::<Vec<_>>(),
+                None => (0..positions.len() as u32).collect(),
+            };
+            let normals = match reader.read_normals() {
+                Some(n) => n.collect::<Vec<_>>(),
+                None => Self::smooth_normals(&positions, &indices),
+            };
+            let colors = match reader.read_colors(0) {
+                Some(c) => c.into_rgba_f32().collect::<Vec<_>>(),
+                None => vec![[1.0; 4]; positions.len()],
+            };
+            let uvs = match reader.read_tex_coords(0) {
+                Some(t) => t.into_f32().collect::<Vec<_>>(),
+                None => vec![[0.0; 2]; positions.len()],
+            };
+
+            self.vertices.extend(
+                positions
+                    .iter()
+                    .zip(normals)
+                    .zip(colors)
+                    .zip(uvs)
+                    .map(|(((pos, norm), color), uv)| {
+                        let pos = transform * Vec4::new(pos[0], pos[1], pos[2], 1.0);
+                        let norm = (normal_mat * Vec3::from(norm)).normalize();
+                        GPUVertex {
+                            pos: [pos.x, pos.y, pos.z, 1.0],
+                            norm: [norm.x, norm.y, norm.z, 1.0],
+                            color,
+                            uv,
+                        }
+                    }),
+            );
+
+            let start = self.indices.len() as u32;
+            self.indices
+                .extend(indices.iter().map(|i| i + first_vertex));
+            self.primitives.push(Primitive {
+                node,
+                mesh: mesh.index(),
+                index: primitive.index(),
+                material: primitive.material().index(),
+                indices: start..self.indices.len() as u32,
+            });
+        }
+    }
+
+    fn add_node(
+        &mut self,
+        node: gltf::Node,
+        parent: &Mat4,
+        buffer_data: &[Vec<u8>],
+    ) {
+        let transform = parent * Mat4::from(node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            let first_vertex = self.vertices.len();
+            self.add_mesh(mesh, Some(node.index()), &transform, buffer_data);
+            if self.vertices.len() > first_vertex {
+                self.node_bounds
+                    .push(Aabb::from_verts(&self.vertices[first_vertex..]));
+            }
+        }
+        if let Some(camera) = node.camera() {
+            self.cameras.push(SceneCamera {
+                name: camera
+                    .name()
+                    .or(node.name())
+                    .map(str::to_owned)
+                    .unwrap_or_else(|| format!("#{}", camera.index())),
+                transform,
+                xmag: match camera.projection() {
+                    gltf::camera::Projection::Orthographic(o) => Some(o.xmag()),
+                    gltf::camera::Projection::Perspective(_) => None,
+                },
+            });
+        }
+        for child in node.children() {
+            self.add_node(child, &transform, buffer_data);
+        }
+    }
+
+    /// Builds area-weighted vertex normals for primitives which lack them
+    fn smooth_normals(positions: &[[f32; 3]], indices: &[u32]) -> Vec<[f32; 3]> {
+        let mut normals = vec![Vec3::zeros(); positions.len()];
+        for tri in indices.chunks_exact(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| Vec3::from(positions[i as usize]));
+            let n = (b - a).cross(&(c - a));
+            for i in tri {
+                normals[*i as usize] += n;
+            }
+        }
+        normals
+            .into_iter()
+            .map(|n| n.try_normalize(1e-12).unwrap_or(Vec3::y()).into())
+            .collect()
+    }
 }
 
 impl Model {
-    pub fn new(
+    pub(crate) fn new(
         device: &wgpu::Device,
-        swapchain_format: wgpu::TextureFormat,
+        format: wgpu::TextureFormat,
         gltf: &Gltf,
-    ) -> (Self, Vec<GPUVertex>) {
+        environment: &Environment,
+        shadow: &Shadow,
+    ) -> Result<Self, String> {
         // starts
         // Load buffers
         let mut buffer_data = Vec::new();
@@ -40,108 +422,399 @@ impl Model {
                     if let Some(blob) = gltf.blob.clone() {
                         blob
                     } else {
-                        panic!("Missing Blob");
+                        return Err("Missing Blob".to_string());
                     }
                 }
-                _ => panic!("Only GLB/embedded buffers supported"),
+                _ => return Err("Only GLB/embedded buffers supported".to_string()),
             };
 
             buffer_data.push(bin);
         }
 
-        let mesh = gltf.meshes().next().unwrap();
-        let primitive = mesh.primitives().next().unwrap();
+        // Walk the scene graph, flattening every mesh into world space.  If
+        // there's no scene, then fall back to drawing each mesh in place.
+        let mut geometry = Geometry::default();
+        match gltf.default_scene().or_else(|| gltf.scenes().next()) {
+            Some(scene) => {
+                for node in scene.nodes() {
+                    geometry.add_node(node, &Mat4::identity(), &buffer_data);
+                }
+            }
+            None => {
+                for mesh in gltf.meshes() {
+                    geometry.add_mesh(mesh, None, &Mat4::identity(), &buffer_data);
+                }
+            }
+        }
+        let Geometry {
+            vertices,
+            indices,
+            primitives,
+            node_bounds,
+            cameras,
+        } = geometry;
+        if vertices.is_empty() {
+            return Err("Model contains no triangles".to_string());
+        }
 
-        let reader = primitive.reader(|buffer| Some(&buffer_data[buffer.index()]));
+        // Material 0 is the default material, followed by the glTF materials
+        let materials = std::iter::once(GPUMaterial::default())
+            .chain(gltf.materials().map(GPUMaterial::from))
+            .collect::<Vec<_>>();
+        let gpu = Gpu::new(device, format, &vertices, &indices, &materials, environment, shadow);
+
+        Ok(Model {
+            gpu,
+            bvh: Bvh::new(&vertices, &indices),
+            normal_length: Aabb::from_verts(&vertices).radius() * 0.02,
+            vertices,
+            indices,
+            materials,
+            primitives,
+            node_bounds,
+            cameras,
+            selected: None,
+            view_mode: ViewMode::Shaded,
+        })
+    }
 
-        let (positions, normals, colors) = (
-            reader.read_positions().unwrap(),
-            reader.read_normals().unwrap(),
-            reader.read_colors(0).unwrap().into_rgba_f32(),
+    /// Recreates the GPU resources from the retained CPU-side data, e.g. on a
+    /// new device after the old one was lost
+    pub(crate) fn rebuild(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        environment: &Environment,
+        shadow: &Shadow,
+    ) {
+        self.gpu = Gpu::new(
+            device,
+            format,
+            &self.vertices,
+            &self.indices,
+            &self.materials,
+            environment,
+            shadow,
         );
+    }
+
+    /// Returns the model's vertices, in world space
+    pub(crate) fn vertices(&self) -> &[GPUVertex] {
+        &self.vertices
+    }
+
+    pub fn primitives(&self) -> &[Primitive] {
+        &self.primitives
+    }
+
+    /// Casts a world-space ray against the model, returning the closest hit
+    pub fn pick(&self, origin: Vec3, dir: Vec3) -> Option<Pick> {
+        let hit = self.bvh.cast(origin, dir)?;
+        // Primitives are stored in index order, so search by first index
+        let first_index = hit.triangle * 3;
+        let primitive = self
+            .primitives
+            .partition_point(|p| p.indices.end <= first_index);
+        Some(Pick {
+            primitive,
+            triangle: (first_index - self.primitives[primitive].indices.start) / 3,
+            position: hit.position,
+        })
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
 
-        let indices = reader.read_indices().map(|indices| indices.into_u32());
-        let indices = match indices {
-            Some(indices) => indices.collect::<Vec<_>>(),
-            None => (0..positions.len() as u32).collect(),
+    /// Highlights a primitive, or clears the highlight
+    pub fn set_selected(&mut self, primitive: Option<usize>) {
+        self.selected = primitive;
+    }
+
+    /// Returns the glTF cameras in the scene, in world space
+    pub fn cameras(&self) -> &[SceneCamera] {
+        &self.cameras
+    }
+
+    /// Returns the world-space bounds of each mesh node
+    pub fn node_bounds(&self) -> &[Aabb] {
+        &self.node_bounds
+    }
+
+    pub fn view_mode(&self) -> ViewMode {
+        self.view_mode
+    }
+
+    pub fn set_view_mode(&mut self, mode: ViewMode) {
+        self.view_mode = mode;
+    }
+
+    /// Updates the uniform buffers with our new matrices.  If `mirror_height`
+    /// is given, then the mirrored uniforms are updated to reflect the model
+    /// about a horizontal plane at that height.
+    pub(crate) fn update(
+        &self,
+        camera: &Camera,
+        queue: &wgpu::Queue,
+        mirror_height: Option<f32>,
+    ) {
+        let eye = camera.eye_dir();
+        let model_mat = camera.model_matrix();
+        let locals = Locals {
+            view_mat: camera.view_matrix().into(),
+            model_mat: model_mat.into(),
+            eye: [eye.x, eye.y, eye.z, 1.0],
+            mode: self.view_mode as u32,
+            normal_length: self.normal_length,
+            selected: self.selected.map(|i| i as u32 + 1).unwrap_or(0),
+            _padding: 0,
         };
+        queue.write_buffer(&self.gpu.uniform_buf, 0, bytemuck::bytes_of(&locals));
 
-        let vertices = positions
-            .zip(normals)
-            .zip(colors)
-            .map(|((pos, norm), color)| GPUVertex {
-                pos: [pos[0], pos[1], pos[2], 1.0],
-                norm: [norm[0], norm[1], norm[2], 1.0],
-                color,
-            })
-            .collect::<Vec<_>>();
+        if let Some(h) = mirror_height {
+            let i = Mat4::identity();
+            let mirror = glm::translate(&i, &Vec3::new(0.0, 2.0 * h, 0.0))
+                * glm::scale(&i, &Vec3::new(1.0, -1.0, 1.0));
+            // Looking at the mirrored model is equivalent to looking at the
+            // original model from a mirrored viewpoint
+            let locals = Locals {
+                model_mat: (model_mat * mirror).into(),
+                eye: [eye.x, -eye.y, eye.z, -1.0],
+                ..locals
+            };
+            queue.write_buffer(&self.gpu.mirror_uniform_buf, 0, bytemuck::bytes_of(&locals));
+        }
+    }
+
+    pub(crate) fn draw(
+        &self,
+        environment: &Environment,
+        shadow: &Shadow,
+        view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+            }),
+            ..Default::default()
+        });
+        self.draw_primitives(&mut rpass, &self.gpu.bind_group, environment, shadow);
+
+        match self.view_mode {
+            ViewMode::Wireframe => {
+                rpass.set_pipeline(&self.gpu.wire_pipeline);
+                rpass.set_index_buffer(
+                    self.gpu.wire_index_buf.slice(..),
+                    wgpu::IndexFormat::Uint32,
+                );
+                rpass.draw_indexed(0..self.gpu.wire_index_count, 0, 0..1);
+            }
+            ViewMode::Normals => {
+                rpass.set_pipeline(&self.gpu.normal_pipeline);
+                rpass.draw(0..2, 0..self.vertices.len() as u32);
+            }
+            _ => (),
+        }
+    }
+
+    /// Draws the model mirrored about the ground plane into a cleared target,
+    /// using the uniforms from the last call to `update` with a mirror height
+    pub(crate) fn draw_mirrored(
+        &self,
+        environment: &Environment,
+        shadow: &Shadow,
+        view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("mirrored model"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: wgpu::StoreOp::Store,
+                }),
+            }),
+            ..Default::default()
+        });
+        self.draw_primitives(&mut rpass, &self.gpu.mirror_bind_group, environment, shadow);
+    }
+
+    fn draw_primitives<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        environment: &'a Environment,
+        shadow: &'a Shadow,
+    ) {
+        rpass.set_pipeline(&self.gpu.render_pipeline);
+        rpass.set_index_buffer(self.gpu.index_buf.slice(..), wgpu::IndexFormat::Uint32);
+        rpass.set_vertex_buffer(0, self.gpu.vertex_buf.slice(..));
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.set_bind_group(2, environment.bind_group(), &[]);
+        rpass.set_bind_group(3, shadow.bind_group(), &[]);
+        for (i, p) in self.primitives.iter().enumerate() {
+            let material = p.material.map(|m| m + 1).unwrap_or(0) as wgpu::BufferAddress;
+            rpass.set_bind_group(
+                1,
+                &self.gpu.material_bind_group,
+                &[(material * self.gpu.material_stride) as wgpu::DynamicOffset],
+            );
+            rpass.set_stencil_reference((self.selected == Some(i)) as u32);
+            // The instance index tells the shader which primitive this is
+            let i = i as u32;
+            rpass.draw_indexed(p.indices.clone(), 0, i..i + 1);
+        }
+    }
+
+    /// Draws every primitive without binding any materials, for passes which
+    /// only need positions (e.g. the shadow map)
+    pub(crate) fn draw_geometry<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        rpass.set_index_buffer(self.gpu.index_buf.slice(..), wgpu::IndexFormat::Uint32);
+        rpass.set_vertex_buffer(0, self.gpu.vertex_buf.slice(..));
+        for p in &self.primitives {
+            rpass.draw_indexed(p.indices.clone(), 0, 0..1);
+        }
+    }
+}
 
+impl Gpu {
+    fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        vertices: &[GPUVertex],
+        indices: &[u32],
+        materials: &[GPUMaterial],
+        environment: &Environment,
+        shadow: &Shadow,
+    ) -> Self {
         let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
+            contents: bytemuck::cast_slice(vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
         let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(&indices),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // Each edge shared between triangles is only drawn once
+        let mut edges = HashSet::new();
+        for tri in indices.chunks_exact(3) {
+            for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+                edges.insert((a.min(b), a.max(b)));
+            }
+        }
+        let wire_indices = edges.into_iter().flat_map(|(a, b)| [a, b]).collect::<Vec<_>>();
+        let wire_index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Wireframe Index Buffer"),
+            contents: bytemuck::cast_slice(&wire_indices),
             usage: wgpu::BufferUsages::INDEX,
         });
 
         let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Uniform Buffer"),
-            size: std::mem::size_of::<Mat4>() as wgpu::BufferAddress * 2,
+            size: std::mem::size_of::<Locals>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mirror_uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Mirror Uniform Buffer"),
+            size: std::mem::size_of::<Locals>() as wgpu::BufferAddress,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as usize;
+        let material_size = std::mem::size_of::<GPUMaterial>();
+        let material_stride = material_size.div_ceil(alignment) * alignment;
+        let mut material_data = vec![0u8; material_stride * materials.len()];
+        for (i, m) in materials.iter().enumerate() {
+            material_data[i * material_stride..i * material_stride + material_size]
+                .copy_from_slice(bytemuck::bytes_of(m));
+        }
+        let material_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material Buffer"),
+            contents: &material_data,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
-                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Mat4>() as u64 * 2),
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Locals>() as u64),
                 },
                 count: None,
             }],
         });
 
+        let material_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("material"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(material_size as u64),
+                    },
+                    count: None,
+                }],
+            });
+
         // Create pipeline layout
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[&bind_group_layout],
+            bind_group_layouts: &[
+                &bind_group_layout,
+                &material_bind_group_layout,
+                environment.bind_group_layout(),
+                shadow.bind_group_layout(),
+            ],
             push_constant_ranges: &[],
         });
 
-        let vertex_buf_layout = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<GPUVertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                // Positions
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                // Normals
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: std::mem::size_of::<Vec4>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                },
-                // Colors
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: 2 * std::mem::size_of::<Vec4>() as wgpu::BufferAddress,
-                    shader_location: 2,
-                },
-            ],
-        };
-
-        // Create bind group
+        // Create bind groups
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &bind_group_layout,
             entries: &[wgpu::BindGroupEntry {
@@ -151,6 +824,28 @@ impl Model {
             label: None,
         });
 
+        let mirror_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: mirror_uniform_buf.as_entire_binding(),
+            }],
+            label: None,
+        });
+
+        let material_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &material_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &material_buf,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(material_size as u64),
+                }),
+            }],
+            label: Some("material"),
+        });
+
         // Load the shaders from disk, either at runtime or compile-time
         let model_src = Cow::Borrowed(include_str!("model.wgsl"));
 
@@ -159,27 +854,40 @@ impl Model {
             source: wgpu::ShaderSource::Wgsl(model_src),
         });
 
+        let stencil_mark = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Always,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Replace,
+        };
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main".into(),
-                buffers: &[vertex_buf_layout],
+                buffers: &[GPUVertex::desc()],
                 // compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: "fs_main".into(),
-                targets: &[Some(swapchain_format.into())],
+                targets: &[Some(format.into())],
                 // compilation_options: Default::default(),
             }),
             primitive: wgpu::PrimitiveState::default(),
             depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
+                format: DEPTH_FORMAT,
                 depth_write_enabled: true,
                 depth_compare: wgpu::CompareFunction::Greater,
-                stencil: wgpu::StencilState::default(),
+                // Visible pixels of the selected primitive are marked in the
+                // stencil buffer, for the selection outline
+                stencil: wgpu::StencilState {
+                    front: stencil_mark,
+                    back: stencil_mark,
+                    read_mask: 0xff,
+                    write_mask: 0xff,
+                },
                 bias: wgpu::DepthBiasState::default(),
             }),
             multisample: wgpu::MultisampleState::default(),
@@ -187,66 +895,59 @@ impl Model {
             // cache: None,
         });
 
-        (
-            Model {
-                render_pipeline,
-                index_buf,
-                vertex_buf,
-                uniform_buf,
-                bind_group,
-                index_count: indices.len() as u32, // index_count: tris.len() as u32 * 3,
-            },
-            vertices,
-        )
-    }
-
-    pub fn draw(
-        &self,
-        camera: &Camera,
-        queue: &wgpu::Queue,
-        frame: &wgpu::SurfaceTexture,
-        depth_view: &wgpu::TextureView,
-        encoder: &mut wgpu::CommandEncoder,
-    ) {
-        // Update the uniform buffer with our new matrix
-        let view_mat = camera.view_matrix();
-        let model_mat = camera.model_matrix();
-        queue.write_buffer(
-            &self.uniform_buf,
-            0,
-            bytemuck::cast_slice(view_mat.as_slice()),
-        );
-        queue.write_buffer(
-            &self.uniform_buf,
-            std::mem::size_of::<Mat4>() as wgpu::BufferAddress,
-            bytemuck::cast_slice(model_mat.as_slice()),
-        );
-
-        let view = frame.texture.create_view(&Default::default());
-        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: None,
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: wgpu::StoreOp::Store,
+        // Debug lines are drawn on top of the shaded model, so they're
+        // nudged towards the viewer in the shader and don't write depth
+        let line_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("debug lines"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let line_pipeline = |entry_point, buffer| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("debug lines"),
+                layout: Some(&line_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point,
+                    buffers: &[buffer],
                 },
-            })],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &depth_view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: wgpu::StoreOp::Store,
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_line",
+                    targets: &[Some(format.into())],
                 }),
-                stencil_ops: None,
-            }),
-            ..Default::default()
-        });
-        rpass.set_pipeline(&self.render_pipeline);
-        rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint32);
-        rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
-        rpass.set_bind_group(0, &self.bind_group, &[]);
-        rpass.draw_indexed(0..self.index_count, 0, 0..1);
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::GreaterEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let wire_pipeline = line_pipeline("vs_wire", GPUVertex::desc());
+        let normal_pipeline = line_pipeline("vs_normal", GPUVertex::instance_desc());
+
+        Gpu {
+            render_pipeline,
+            index_buf,
+            vertex_buf,
+            uniform_buf,
+            bind_group,
+            mirror_uniform_buf,
+            mirror_bind_group,
+            material_bind_group,
+            material_stride: material_stride as wgpu::BufferAddress,
+            wire_index_buf,
+            wire_index_count: wire_indices.len() as u32,
+            wire_pipeline,
+            normal_pipeline,
+        }
     }
 }
@@ -2,29 +2,260 @@ struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) normal: vec4<f32>,
     @location(1) color: vec4<f32>,
+    @location(2) world_pos: vec4<f32>,
+    @location(3) uv: vec2<f32>,
+    // Index of the primitive, passed as the instance index
+    @location(4) @interpolate(flat) primitive: u32,
 };
 
 struct Locals {
     view_mat: mat4x4<f32>,
     model_mat: mat4x4<f32>,
+    eye: vec4<f32>,
+    mode: u32,
+    normal_length: f32,
+    // Index of the highlighted primitive plus one, or 0 if none is
+    selected: u32,
 };
 @group(0) @binding(0)
 var<uniform> r_locals: Locals;
 
+struct Material {
+    base_color: vec4<f32>,
+    emissive: vec4<f32>,
+    metallic: f32,
+    roughness: f32,
+};
+@group(1) @binding(0)
+var<uniform> r_material: Material;
+
+@group(2) @binding(0)
+var r_irradiance: texture_cube<f32>;
+@group(2) @binding(1)
+var r_specular: texture_cube<f32>;
+@group(2) @binding(2)
+var r_brdf_lut: texture_2d<f32>;
+@group(2) @binding(3)
+var r_env_sampler: sampler;
+
+struct Light {
+    view_proj: mat4x4<f32>,
+    direction: vec4<f32>,
+    color: vec4<f32>,
+    shadows: u32,
+};
+@group(3) @binding(0)
+var<uniform> r_light: Light;
+@group(3) @binding(1)
+var r_shadow_map: texture_depth_2d;
+@group(3) @binding(2)
+var r_shadow_sampler: sampler_comparison;
+
+const PI: f32 = 3.14159265359;
+
+// Must match SHADOW_SIZE in shadow.rs
+const SHADOW_SIZE: f32 = 2048.0;
+
+// Tint mixed into the selected primitive
+const HIGHLIGHT: vec3<f32> = vec3<f32>(1.0, 0.5, 0.1);
+
+// Must match ViewMode in model.rs
+const MODE_WORLD_NORMALS: u32 = 3u;
+const MODE_UV_CHECKER: u32 = 4u;
+const MODE_VERTEX_COLOR: u32 = 5u;
+const MODE_PRIMITIVES: u32 = 6u;
+
+// Must match SPECULAR_MIPS - 1 in environment.rs
+const SPECULAR_MAX_LOD: f32 = 4.0;
+
 @vertex
 fn vs_main(
     @location(0) position: vec4<f32>,
     @location(1) normal: vec4<f32>,
     @location(2) color: vec4<f32>,
+    @location(3) uv: vec2<f32>,
+    @builtin(instance_index) primitive: u32,
 ) -> VertexOutput {
     var out: VertexOutput;
     out.position = r_locals.view_mat * r_locals.model_mat * vec4<f32>(position.xyz, 1.0);
-    out.normal = normalize(r_locals.model_mat * vec4<f32>(normal.xyz, 0.0));
+    // Vertices are already in world space, so lighting happens there too
+    out.normal = vec4<f32>(normal.xyz, 0.0);
     out.color = color;
+    out.world_pos = vec4<f32>(position.xyz, 1.0);
+    out.uv = uv;
+    out.primitive = primitive;
+    return out;
+}
+
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
+    return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0)
+        * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    return (n_dot_v / (n_dot_v * (1.0 - k) + k)) * (n_dot_l / (n_dot_l * (1.0 - k) + k));
+}
+
+// Returns the fraction of the key light reaching this point, using a 3x3
+// PCF kernel over the shadow map
+fn shadow_factor(world_pos: vec4<f32>) -> f32 {
+    if (r_light.shadows == 0u) {
+        return 1.0;
+    }
+    let clip = r_light.view_proj * world_pos;
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0) {
+        return 1.0;
+    }
+    var total = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) / SHADOW_SIZE;
+            total += textureSampleCompareLevel(r_shadow_map, r_shadow_sampler, uv + offset, ndc.z);
+        }
+    }
+    return total / 9.0;
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return pow(c, vec3<f32>(2.2));
+}
+
+// PCG hash, used to give each primitive a stable random color
+fn hash(x: u32) -> u32 {
+    let state = x * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Fully saturated color for a hue in [0, 1)
+fn hue(h: f32) -> vec3<f32> {
+    let k = vec3<f32>(0.0, 2.0 / 3.0, 1.0 / 3.0);
+    return clamp(abs(fract(h + k) * 6.0 - 3.0) - 1.0, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    var n = normalize(in.normal.xyz);
+    // Mirrored geometry has flipped winding, so front_facing is inverted
+    if (front_facing != (r_locals.eye.w > 0.0)) {
+        n = -n;
+    }
+    let v = normalize(r_locals.eye.xyz);
+
+    // Unlit debug modes, with a little view-dependent shading to show form
+    if (r_locals.mode >= MODE_WORLD_NORMALS) {
+        var color: vec3<f32>;
+        switch r_locals.mode {
+            case MODE_WORLD_NORMALS: {
+                color = srgb_to_linear(n * 0.5 + 0.5);
+            }
+            case MODE_UV_CHECKER: {
+                let cell = floor(in.uv * 8.0);
+                let checker = (i32(cell.x) + i32(cell.y)) & 1;
+                let tint = vec3<f32>(fract(in.uv), 1.0);
+                color = srgb_to_linear(mix(vec3<f32>(0.15), tint, f32(checker) * 0.8 + 0.2));
+            }
+            case MODE_VERTEX_COLOR: {
+                color = in.color.rgb;
+            }
+            default: {
+                color = srgb_to_linear(hue(f32(hash(in.primitive)) / 4294967295.0));
+            }
+        }
+        color *= 0.6 + 0.4 * max(dot(n, v), 0.0);
+        if (in.primitive + 1u == r_locals.selected) {
+            color = mix(color, HIGHLIGHT, 0.35);
+        }
+        return vec4<f32>(color, 1.0);
+    }
+
+    let base_color = in.color * r_material.base_color;
+    let metallic = clamp(r_material.metallic, 0.0, 1.0);
+    let roughness = clamp(r_material.roughness, 0.04, 1.0);
+
+    let n_dot_v = max(dot(n, v), 1e-4);
+    let f0 = mix(vec3<f32>(0.04), base_color.rgb, metallic);
+    let f = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    let kd = (1.0 - f) * (1.0 - metallic);
+
+    let irradiance = textureSample(r_irradiance, r_env_sampler, n).rgb;
+    let diffuse = kd * irradiance * base_color.rgb;
+
+    let r = reflect(-v, n);
+    let prefiltered = textureSampleLevel(
+        r_specular, r_env_sampler, r, roughness * SPECULAR_MAX_LOD).rgb;
+    let brdf = textureSample(r_brdf_lut, r_env_sampler, vec2<f32>(n_dot_v, roughness)).rg;
+    let specular = prefiltered * (f * brdf.x + brdf.y);
+
+    // Direct lighting from the key light
+    let l = normalize(r_light.direction.xyz);
+    let h = normalize(v + l);
+    let n_dot_l = max(dot(n, l), 0.0);
+    let f_direct = f0 + (1.0 - f0) * pow(1.0 - max(dot(h, v), 0.0), 5.0);
+    let spec_direct = distribution_ggx(max(dot(n, h), 0.0), roughness)
+        * geometry_smith(n_dot_v, n_dot_l, roughness) * f_direct
+        / (4.0 * n_dot_v * n_dot_l + 1e-4);
+    let kd_direct = (1.0 - f_direct) * (1.0 - metallic);
+    let direct = (kd_direct * base_color.rgb / PI + spec_direct)
+        * r_light.color.rgb * n_dot_l * shadow_factor(in.world_pos);
+
+    var color = diffuse + specular + direct + r_material.emissive.rgb;
+    if (in.primitive + 1u == r_locals.selected) {
+        color = mix(color, HIGHLIGHT, 0.35);
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Debug lines, drawn over the shaded model
+
+struct LineOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+// Pulls a line's clip-space position slightly towards the viewer, so that it
+// isn't hidden by the surface it lies on
+fn line_position(world_pos: vec3<f32>) -> vec4<f32> {
+    var p = r_locals.view_mat * r_locals.model_mat * vec4<f32>(world_pos, 1.0);
+    p.z += 1e-4 * p.w;
+    return p;
+}
+
+@vertex
+fn vs_wire(@location(0) position: vec4<f32>) -> LineOutput {
+    var out: LineOutput;
+    out.position = line_position(position.xyz);
+    out.color = vec4<f32>(0.02, 0.02, 0.02, 1.0);
+    return out;
+}
+
+// Draws a line from each vertex (one per instance) along its normal
+@vertex
+fn vs_normal(
+    @builtin(vertex_index) in_vertex_index: u32,
+    @location(0) position: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+) -> LineOutput {
+    var out: LineOutput;
+    let t = f32(in_vertex_index) * r_locals.normal_length;
+    out.position = line_position(position.xyz + normal.xyz * t);
+    out.color = vec4<f32>(srgb_to_linear(normal.xyz * 0.5 + 0.5), 1.0);
     return out;
 }
 
 @fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(abs(in.normal.z) * in.color.xyz, 1.0);
+fn fs_line(in: LineOutput) -> @location(0) vec4<f32> {
+    return in.color;
 }
@@ -0,0 +1,389 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::Arg;
+
+use glb_to_webgpu::{
+    wgpu, BackdropStyle, EffectKind, GroundOptions, GroundStyle, OverlayKind, RenderOptions,
+    ToneMapping, ViewMode,
+};
+
+use crate::{
+    bindings::{Action, Binding, Bindings},
+    schedule::{MIN_FPS, RedrawMode},
+};
+
+/// How the GPU adapter is picked and the window's surface presented
+#[derive(Clone, Debug)]
+pub struct GpuOptions {
+    pub backends: wgpu::Backends,
+    pub power_preference: wgpu::PowerPreference,
+    /// Case-insensitive part of the name of the adapter to use
+    pub adapter: Option<String>,
+    /// Whether to use the fallback (usually software) adapter
+    pub fallback_adapter: bool,
+    pub present_mode: wgpu::PresentMode,
+}
+
+/// Viewer settings, as parsed from the command line
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// GLB file to view, or `None` for the built-in axis model
+    pub model: Option<PathBuf>,
+
+    /// How the scene is drawn.  Its view mode is only the initial one, which
+    /// can be cycled at runtime from the keyboard.
+    pub render: RenderOptions,
+
+    /// Whether rotation keeps going after a drag, with smoothed zooming
+    pub inertia: bool,
+
+    /// Name of the camera bookmark to start at
+    pub bookmark: Option<String>,
+
+    /// Keyboard shortcuts, i.e. the defaults with any `--bind` overrides
+    pub bindings: Bindings,
+
+    pub gpu: GpuOptions,
+
+    pub redraw: RedrawMode,
+
+    /// Frame-rate cap, or `None` to draw as fast as presentation allows
+    pub max_fps: Option<f32>,
+
+    /// Whether to list the available adapters and exit, rather than viewing
+    pub list_adapters: bool,
+}
+
+impl Options {
+    /// Parses the process's command line
+    pub fn from_args() -> Self {
+        Self::parse_from(std::env::args_os())
+    }
+
+    /// Parses a command line, starting with the program name
+    pub fn parse_from<I, T>(args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let bind_help = format!(
+            "Binds a key to {} or none (may be repeated)",
+            Action::names().join(", ")
+        );
+        let matches = clap::App::new("glb-to-webgpu")
+            .about("Views a GLB model with WebGPU")
+            .arg(
+                Arg::with_name("model")
+                    .value_name("MODEL")
+                    .index(1)
+                    .help("GLB file to view (defaults to a built-in set of axes)"),
+            )
+            .arg(
+                Arg::with_name("backdrop")
+                    .long("backdrop")
+                    .value_name("STYLE")
+                    .takes_value(true)
+                    .validator(|s| s.parse::<BackdropStyle>().map(|_| ()))
+                    .help(
+                        "Background behind the model: solid:#rrggbb, \
+                         gradient:#rrggbb:#rrggbb[:angle], image:<path> or env:<path.hdr>",
+                    ),
+            )
+            .arg(
+                Arg::with_name("environment")
+                    .long("environment")
+                    .value_name("PATH")
+                    .takes_value(true)
+                    .help(
+                        "Equirectangular .hdr / .exr map used to light the model \
+                         (defaults to the env: backdrop, if any)",
+                    ),
+            )
+            .arg(
+                Arg::with_name("no-shadows")
+                    .long("no-shadows")
+                    .help("Starts with shadows from the key light turned off"),
+            )
+            .arg(
+                Arg::with_name("ground")
+                    .long("ground")
+                    .value_name("STYLE")
+                    .takes_value(true)
+                    .possible_values(&["off", "shadow", "contact"])
+                    .help("Ground plane beneath the model (defaults to off)"),
+            )
+            .arg(
+                Arg::with_name("ground-reflection")
+                    .long("ground-reflection")
+                    .help("Shows a faint reflection of the model in the ground plane"),
+            )
+            .arg(
+                Arg::with_name("tonemap")
+                    .long("tonemap")
+                    .value_name("OPERATOR")
+                    .takes_value(true)
+                    .possible_values(&["aces", "agx", "reinhard", "neutral"])
+                    .help("Tone mapping operator (defaults to neutral)"),
+            )
+            .arg(
+                Arg::with_name("exposure")
+                    .long("exposure")
+                    .value_name("EV")
+                    .takes_value(true)
+                    .allow_hyphen_values(true)
+                    .validator(|s| {
+                        s.parse::<f32>()
+                            .map(|_| ())
+                            .map_err(|_| format!("Invalid exposure '{}'", s))
+                    })
+                    .help("Exposure adjustment in stops (defaults to 0)"),
+            )
+            .arg(
+                Arg::with_name("effects")
+                    .long("effects")
+                    .value_name("LIST")
+                    .takes_value(true)
+                    .validator(|s| Self::parse_list::<EffectKind>(&s).map(|_| ()))
+                    .help(
+                        "Comma-separated post-processing effects: ssao, bloom, fxaa \
+                         or none (defaults to bloom,fxaa)",
+                    ),
+            )
+            .arg(
+                Arg::with_name("view")
+                    .long("view")
+                    .value_name("MODE")
+                    .takes_value(true)
+                    .possible_values(&[
+                        "shaded",
+                        "wireframe",
+                        "normals",
+                        "world-normals",
+                        "uv",
+                        "vertex-color",
+                        "primitives",
+                    ])
+                    .help("Debug view mode (defaults to shaded; the V key cycles modes)"),
+            )
+            .arg(
+                Arg::with_name("overlays")
+                    .long("overlays")
+                    .value_name("LIST")
+                    .takes_value(true)
+                    .validator(|s| Self::parse_list::<OverlayKind>(&s).map(|_| ()))
+                    .help(
+                        "Comma-separated overlays: bounds, nodes, axes, grid \
+                         or none (the default)",
+                    ),
+            )
+            .arg(
+                Arg::with_name("inertia")
+                    .long("inertia")
+                    .help("Keeps the view spinning after a drag and smooths zooming"),
+            )
+            .arg(
+                Arg::with_name("bookmark")
+                    .long("bookmark")
+                    .value_name("NAME")
+                    .takes_value(true)
+                    .help("Starts at a camera bookmark saved for the model"),
+            )
+            .arg(
+                Arg::with_name("bind")
+                    .long("bind")
+                    .value_name("KEY=ACTION")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .validator(|s| s.parse::<Binding>().map(|_| ()))
+                    .help(&bind_help),
+            )
+            .arg(
+                Arg::with_name("backend")
+                    .long("backend")
+                    .value_name("LIST")
+                    .takes_value(true)
+                    .validator(|s| Self::parse_backends(&s).map(|_| ()))
+                    .help(
+                        "Comma-separated graphics APIs to try: vulkan, metal, dx12, gl \
+                         or browser (defaults to all of them)",
+                    ),
+            )
+            .arg(
+                Arg::with_name("power")
+                    .long("power")
+                    .value_name("PREFERENCE")
+                    .takes_value(true)
+                    .possible_values(&["low", "high"])
+                    .help("Prefers a low-power or high-performance adapter"),
+            )
+            .arg(
+                Arg::with_name("adapter")
+                    .long("adapter")
+                    .value_name("NAME")
+                    .takes_value(true)
+                    .conflicts_with_all(&["power", "fallback-adapter"])
+                    .help("Uses the adapter whose name contains NAME (see --list-adapters)"),
+            )
+            .arg(
+                Arg::with_name("fallback-adapter")
+                    .long("fallback-adapter")
+                    .help("Uses the fallback software adapter"),
+            )
+            .arg(
+                Arg::with_name("present-mode")
+                    .long("present-mode")
+                    .value_name("MODE")
+                    .takes_value(true)
+                    .possible_values(&[
+                        "fifo",
+                        "fifo-relaxed",
+                        "mailbox",
+                        "immediate",
+                        "auto-vsync",
+                        "auto-no-vsync",
+                    ])
+                    .help("How frames are presented to the window (defaults to fifo)"),
+            )
+            .arg(
+                Arg::with_name("redraw")
+                    .long("redraw")
+                    .value_name("MODE")
+                    .takes_value(true)
+                    .possible_values(&["on-demand", "continuous"])
+                    .help(
+                        "Draws frames only when the view changes, or all the time \
+                         (defaults to on-demand)",
+                    ),
+            )
+            .arg(
+                Arg::with_name("max-fps")
+                    .long("max-fps")
+                    .value_name("FPS")
+                    .takes_value(true)
+                    .validator(|s| match s.parse::<f32>() {
+                        Ok(fps) if fps.is_finite() && fps >= MIN_FPS => Ok(()),
+                        _ => Err(format!(
+                            "Invalid frame rate '{}', expected at least {}",
+                            s, MIN_FPS
+                        )),
+                    })
+                    .help("Caps the frame rate (defaults to no cap)"),
+            )
+            .arg(
+                Arg::with_name("list-adapters")
+                    .long("list-adapters")
+                    .help("Lists the available adapters and exits"),
+            )
+            .get_matches_from(args);
+
+        let backdrop = matches
+            .value_of("backdrop")
+            .map(|s| s.parse().unwrap())
+            .unwrap_or_default();
+        let environment = match (matches.value_of("environment"), &backdrop) {
+            (Some(path), _) => Some(path.into()),
+            (None, BackdropStyle::Environment(path)) => Some(path.clone()),
+            _ => None,
+        };
+        let ground = GroundOptions {
+            style: matches
+                .value_of("ground")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(GroundStyle::Off),
+            reflection: matches.is_present("ground-reflection"),
+        };
+        let mut bindings = Bindings::default();
+        for b in matches.values_of("bind").into_iter().flatten() {
+            bindings.bind(&b.parse().unwrap());
+        }
+        let render = RenderOptions {
+            backdrop,
+            environment,
+            shadows: !matches.is_present("no-shadows"),
+            ground,
+            tone_mapping: matches
+                .value_of("tonemap")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(ToneMapping::Neutral),
+            exposure: matches
+                .value_of("exposure")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(0.0),
+            effects: matches
+                .value_of("effects")
+                .map(|s| Self::parse_list(s).unwrap())
+                .unwrap_or_else(|| vec![EffectKind::Bloom, EffectKind::Fxaa]),
+            view_mode: matches
+                .value_of("view")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(ViewMode::Shaded),
+            overlays: matches
+                .value_of("overlays")
+                .map(|s| Self::parse_list(s).unwrap())
+                .unwrap_or_default(),
+        };
+        let gpu = GpuOptions {
+            backends: matches
+                .value_of("backend")
+                .map(|s| Self::parse_backends(s).unwrap())
+                .unwrap_or(wgpu::Backends::all()),
+            power_preference: match matches.value_of("power") {
+                Some("low") => wgpu::PowerPreference::LowPower,
+                Some("high") => wgpu::PowerPreference::HighPerformance,
+                _ => wgpu::PowerPreference::default(),
+            },
+            adapter: matches.value_of("adapter").map(str::to_owned),
+            fallback_adapter: matches.is_present("fallback-adapter"),
+            present_mode: match matches.value_of("present-mode") {
+                Some("fifo-relaxed") => wgpu::PresentMode::FifoRelaxed,
+                Some("mailbox") => wgpu::PresentMode::Mailbox,
+                Some("immediate") => wgpu::PresentMode::Immediate,
+                Some("auto-vsync") => wgpu::PresentMode::AutoVsync,
+                Some("auto-no-vsync") => wgpu::PresentMode::AutoNoVsync,
+                _ => wgpu::PresentMode::Fifo,
+            },
+        };
+        Options {
+            model: matches.value_of("model").map(PathBuf::from),
+            render,
+            inertia: matches.is_present("inertia"),
+            bookmark: matches.value_of("bookmark").map(str::to_owned),
+            bindings,
+            gpu,
+            redraw: matches
+                .value_of("redraw")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(RedrawMode::OnDemand),
+            max_fps: matches.value_of("max-fps").map(|s| s.parse().unwrap()),
+            list_adapters: matches.is_present("list-adapters"),
+        }
+    }
+
+    /// Parses a comma-separated list of backends
+    fn parse_backends(s: &str) -> Result<wgpu::Backends, String> {
+        s.split(',')
+            .map(|b| match b {
+                "vulkan" => Ok(wgpu::Backends::VULKAN),
+                "metal" => Ok(wgpu::Backends::METAL),
+                "dx12" => Ok(wgpu::Backends::DX12),
+                "gl" => Ok(wgpu::Backends::GL),
+                "browser" => Ok(wgpu::Backends::BROWSER_WEBGPU),
+                _ => Err(format!(
+                    "Invalid backend '{}', expected vulkan, metal, dx12, gl or browser",
+                    b
+                )),
+            })
+            .collect()
+    }
+
+    /// Parses a comma-separated list, where `none` is the empty list
+    fn parse_list<T: FromStr<Err = String>>(s: &str) -> Result<Vec<T>, String> {
+        if s == "none" {
+            return Ok(vec![]);
+        }
+        s.split(',').map(str::parse).collect()
+    }
+}
@@ -0,0 +1,51 @@
+struct Locals {
+    color: vec4<f32>,
+    width: f32,
+};
+@group(0) @binding(0)
+var<uniform> r_locals: Locals;
+@group(0) @binding(1)
+var r_stencil: texture_2d<u32>;
+
+// Largest outline width which the search below can reach, in pixels
+const MAX_RADIUS: i32 = 4;
+
+// A single triangle which covers the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(in_vertex_index & 2u) * 2 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+fn selected(p: vec2<i32>, size: vec2<i32>) -> bool {
+    let q = clamp(p, vec2<i32>(0), size - 1);
+    return textureLoad(r_stencil, q, 0).r != 0u;
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(r_stencil));
+    let p = vec2<i32>(pos.xy);
+    if (selected(p, size)) {
+        discard;
+    }
+
+    // Distance to the nearest selected pixel, within the search radius
+    let radius = min(i32(ceil(r_locals.width)), MAX_RADIUS);
+    var dist = f32(MAX_RADIUS + 1);
+    for (var y = -radius; y <= radius; y += 1) {
+        for (var x = -radius; x <= radius; x += 1) {
+            if (selected(p + vec2<i32>(x, y), size)) {
+                dist = min(dist, length(vec2<f32>(f32(x), f32(y))));
+            }
+        }
+    }
+
+    // Antialias the outer edge over one pixel
+    let alpha = clamp(r_locals.width + 0.5 - dist, 0.0, 1.0) * r_locals.color.a;
+    if (alpha <= 0.0) {
+        discard;
+    }
+    return vec4<f32>(r_locals.color.rgb * alpha, alpha);
+}
@@ -0,0 +1,304 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use std::str::FromStr;
+
+use bytemuck::{Pod, Zeroable};
+use glm::Vec3;
+use nalgebra_glm as glm;
+use wgpu::util::DeviceExt;
+
+use crate::{bounds::Aabb, camera::Camera, renderer::DEPTH_FORMAT};
+
+/// Optional reference geometry drawn over the scene
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OverlayKind {
+    /// The bounding box of the whole scene
+    Bounds,
+    /// The bounding box of each node with a mesh
+    NodeBounds,
+    /// X / Y / Z axes at the world origin
+    Axes,
+    /// A reference grid on the ground plane
+    Grid,
+}
+
+impl FromStr for OverlayKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bounds" => Ok(OverlayKind::Bounds),
+            "nodes" => Ok(OverlayKind::NodeBounds),
+            "axes" => Ok(OverlayKind::Axes),
+            "grid" => Ok(OverlayKind::Grid),
+            _ => Err(format!(
+                "Invalid overlay '{}', expected bounds, nodes, axes or grid",
+                s
+            )),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct LineVertex {
+    pos: [f32; 4],
+    color: [f32; 4],
+}
+
+/// Uniforms for `overlay.wgsl`, which must match `Locals` in the shader
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Locals {
+    view_mat: [[f32; 4]; 4],
+    model_mat: [[f32; 4]; 4],
+    /// Grid center (xyz) and spacing between minor lines (w)
+    grid: [f32; 4],
+    /// Distance from the center at which the grid starts fading (x) and is
+    /// completely faded out (y)
+    grid_fade: [f32; 4],
+}
+
+/// Draws line overlays and the reference grid.  The scene is static, so all
+/// of the line geometry is built once up front, and each kind of overlay is
+/// just a range within the shared vertex buffer.
+pub struct Overlay {
+    enabled: Vec<OverlayKind>,
+    locals: Locals,
+
+    uniform_buf: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertex_buf: wgpu::Buffer,
+    lines: Vec<(OverlayKind, Range<u32>)>,
+    line_pipeline: wgpu::RenderPipeline,
+    grid_pipeline: wgpu::RenderPipeline,
+}
+
+impl Overlay {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        bounds: &Aabb,
+        node_bounds: &[Aabb],
+        enabled: &[OverlayKind],
+    ) -> Self {
+        let mut vertices = vec![];
+        let mut lines = vec![];
+        let mut add = |kind, segments: Vec<(Vec3, Vec3, [f32; 4])>| {
+            let start = vertices.len() as u32;
+            for (a, b, color) in segments {
+                vertices.push(LineVertex {
+                    pos: [a.x, a.y, a.z, 1.0],
+                    color,
+                });
+                vertices.push(LineVertex {
+                    pos: [b.x, b.y, b.z, 1.0],
+                    color,
+                });
+            }
+            lines.push((kind, start..vertices.len() as u32));
+        };
+        add(OverlayKind::Bounds, Self::box_lines(bounds, [1.0, 0.8, 0.2, 1.0]));
+        add(
+            OverlayKind::NodeBounds,
+            node_bounds
+                .iter()
+                .flat_map(|b| Self::box_lines(b, [0.2, 0.8, 1.0, 1.0]))
+                .collect(),
+        );
+        let axis = bounds.radius() * 0.5;
+        add(
+            OverlayKind::Axes,
+            vec![
+                (Vec3::zeros(), Vec3::x() * axis, [1.0, 0.1, 0.1, 1.0]),
+                (Vec3::zeros(), Vec3::y() * axis, [0.1, 1.0, 0.1, 1.0]),
+                (Vec3::zeros(), Vec3::z() * axis, [0.1, 0.1, 1.0, 1.0]),
+            ],
+        );
+
+        let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        // Grid lines are spaced at a power of ten which suits the model size,
+        // with major lines every ten cells
+        let radius = bounds.radius().max(1e-6);
+        let center = bounds.center();
+        let locals = Locals {
+            view_mat: glm::Mat4::identity().into(),
+            model_mat: glm::Mat4::identity().into(),
+            grid: [
+                center.x,
+                bounds.min.y,
+                center.z,
+                10f32.powf((radius * 0.2).log10().round()),
+            ],
+            grid_fade: [radius, radius * 4.0, 0.0, 0.0],
+        };
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay Uniform Buffer"),
+            size: std::mem::size_of::<Locals>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("overlay"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Locals>() as u64),
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("overlay"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("overlay.wgsl"))),
+        });
+
+        // Overlays are depth tested against the scene, but don't write depth
+        // themselves, so they never hide each other
+        let pipeline = |label, vs, fs, buffers: &[wgpu::VertexBufferLayout], topology| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vs,
+                    buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::GreaterEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let line_pipeline = pipeline(
+            "overlay lines",
+            "vs_line",
+            "fs_line",
+            &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+            }],
+            wgpu::PrimitiveTopology::LineList,
+        );
+        let grid_pipeline = pipeline(
+            "overlay grid",
+            "vs_grid",
+            "fs_grid",
+            &[],
+            wgpu::PrimitiveTopology::TriangleList,
+        );
+
+        Overlay {
+            enabled: enabled.to_vec(),
+            locals,
+            uniform_buf,
+            bind_group,
+            vertex_buf,
+            lines,
+            line_pipeline,
+            grid_pipeline,
+        }
+    }
+
+    /// Returns the twelve edges of a box as line segments
+    fn box_lines(b: &Aabb, color: [f32; 4]) -> Vec<(Vec3, Vec3, [f32; 4])> {
+        let c = b.corners();
+        // Corners which differ in exactly one bit share an edge
+        (0..8)
+            .flat_map(|i| [1, 2, 4].map(move |bit| (i, i | bit)))
+            .filter(|(i, j)| i != j)
+            .map(|(i, j)| (c[i], c[j], color))
+            .collect()
+    }
+
+    pub fn draw(
+        &mut self,
+        camera: &Camera,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        if self.enabled.is_empty() {
+            return;
+        }
+        self.locals.view_mat = camera.view_matrix().into();
+        self.locals.model_mat = camera.model_matrix().into();
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&self.locals));
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("overlay"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        if self.enabled.contains(&OverlayKind::Grid) {
+            rpass.set_pipeline(&self.grid_pipeline);
+            rpass.draw(0..6, 0..1);
+        }
+        rpass.set_pipeline(&self.line_pipeline);
+        rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+        for (kind, range) in &self.lines {
+            if self.enabled.contains(kind) {
+                rpass.draw(range.clone(), 0..1);
+            }
+        }
+    }
+}
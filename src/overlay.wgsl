@@ -0,0 +1,95 @@
+struct Locals {
+    view_mat: mat4x4<f32>,
+    model_mat: mat4x4<f32>,
+    // Grid center (xyz) and spacing between minor lines (w)
+    grid: vec4<f32>,
+    // Distance at which the grid starts fading (x) and disappears (y)
+    grid_fade: vec4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> r_locals: Locals;
+
+// Pulls a clip-space position slightly towards the viewer, so that overlays
+// lying on a surface aren't hidden by it
+fn nudge(p: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(p.x, p.y, p.z + 1e-4 * p.w, p.w);
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Lines (bounding boxes and axes)
+
+struct LineOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_line(@location(0) position: vec4<f32>, @location(1) color: vec4<f32>) -> LineOutput {
+    var out: LineOutput;
+    out.position = nudge(r_locals.view_mat * r_locals.model_mat * position);
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs_line(in: LineOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Reference grid
+
+struct GridOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_pos: vec3<f32>,
+};
+
+@vertex
+fn vs_grid(@builtin(vertex_index) in_vertex_index: u32) -> GridOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+    );
+    let c = corners[in_vertex_index] * r_locals.grid_fade.y;
+    let g = r_locals.grid;
+    var out: GridOutput;
+    out.world_pos = vec3<f32>(g.x + c.x, g.y, g.z + c.y);
+    out.position = nudge(r_locals.view_mat * r_locals.model_mat * vec4<f32>(out.world_pos, 1.0));
+    return out;
+}
+
+// Returns line coverage for a grid with the given spacing, anti-aliased
+// using screen-space derivatives
+fn grid_lines(coord: vec2<f32>) -> vec2<f32> {
+    let d = fwidth(coord);
+    let g = abs(fract(coord - 0.5) - 0.5) / max(d, vec2<f32>(1e-6));
+    return 1.0 - min(g, vec2<f32>(1.0));
+}
+
+@fragment
+fn fs_grid(in: GridOutput) -> @location(0) vec4<f32> {
+    // World coordinates are used (rather than offsets from the grid center)
+    // so that the grid lines up with the origin
+    let coord = in.world_pos.xz / r_locals.grid.w;
+    let minor = grid_lines(coord);
+    let major = grid_lines(coord * 0.1);
+
+    // Lines along the world axes are tinted like the origin gizmo: the line
+    // at x = 0 runs along Z (blue), and the line at z = 0 runs along X (red)
+    let axis = minor * step(abs(coord), vec2<f32>(0.5));
+    var color = vec3<f32>(0.5);
+    if (axis.x > 0.0) {
+        color = vec3<f32>(0.1, 0.1, 1.0);
+    } else if (axis.y > 0.0) {
+        color = vec3<f32>(1.0, 0.1, 0.1);
+    }
+
+    let dist = length(in.world_pos.xz - r_locals.grid.xz);
+    let fade = 1.0 - smoothstep(r_locals.grid_fade.x, r_locals.grid_fade.y, dist);
+    let alpha = max(max(minor.x, minor.y) * 0.25, max(major.x, major.y) * 0.6) * fade;
+    return vec4<f32>(color * alpha, alpha);
+}
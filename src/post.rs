@@ -0,0 +1,216 @@
+use std::str::FromStr;
+
+use crate::winit::dpi::PhysicalSize;
+use crate::{
+    camera::Camera,
+    tonemap::{Tonemap, HDR_FORMAT},
+};
+
+mod bloom;
+mod fxaa;
+mod ssao;
+
+/// Identifies one of the built-in post-processing effects
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EffectKind {
+    Ssao,
+    Bloom,
+    Fxaa,
+}
+
+impl FromStr for EffectKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ssao" => Ok(EffectKind::Ssao),
+            "bloom" => Ok(EffectKind::Bloom),
+            "fxaa" => Ok(EffectKind::Fxaa),
+            _ => Err(format!(
+                "Invalid effect '{}', expected ssao, bloom or fxaa",
+                s
+            )),
+        }
+    }
+}
+
+/// Which side of tone mapping an effect runs on
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// Linear scene radiance, in `HDR_FORMAT`
+    Hdr,
+    /// Tone-mapped colors, in the output format
+    Display,
+}
+
+/// Everything an effect may need while drawing, besides its input and output
+pub struct Context<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub camera: &'a Camera,
+    /// The scene's depth buffer, with 0 at the far plane
+    pub depth: &'a wgpu::TextureView,
+}
+
+/// A full-screen pass which reads one color target and writes another
+pub trait Effect {
+    fn kind(&self) -> EffectKind;
+    fn stage(&self) -> Stage;
+
+    /// Rebuilds any size-dependent resources
+    fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>);
+
+    /// Reads `input` and writes the processed image into `output`, which is
+    /// a different texture of the same size and format
+    fn draw(
+        &self,
+        ctx: &Context,
+        input: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    );
+}
+
+/// Creates a texture which can be both rendered to and sampled from
+fn target(
+    device: &wgpu::Device,
+    label: &str,
+    format: wgpu::TextureFormat,
+    size: PhysicalSize<u32>,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let tex = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: size.width.max(1),
+            height: size.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = tex.create_view(&Default::default());
+    (tex, view)
+}
+
+/// The chain of effects run after the scene is drawn.  Effects run in a
+/// fixed order, ping-ponging between scratch targets: HDR effects first,
+/// then tone mapping, then display effects, with the last pass writing into
+/// the output.
+///
+/// Bind groups for the passes are built every frame, since each effect's
+/// input depends on which other effects are enabled.
+pub struct Post {
+    output_format: wgpu::TextureFormat,
+    tonemap: Tonemap,
+    effects: Vec<(bool, Box<dyn Effect>)>,
+
+    /// The scene is drawn into this target, which is the chain's input
+    scene: (wgpu::Texture, wgpu::TextureView),
+    hdr: [(wgpu::Texture, wgpu::TextureView); 2],
+    display: [(wgpu::Texture, wgpu::TextureView); 2],
+}
+
+impl Post {
+    pub fn new(
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+        tonemap: Tonemap,
+        enabled: &[EffectKind],
+    ) -> Self {
+        let effects: Vec<Box<dyn Effect>> = vec![
+            Box::new(ssao::Ssao::new(device, size)),
+            Box::new(bloom::Bloom::new(device, size)),
+            Box::new(fxaa::Fxaa::new(device, output_format)),
+        ];
+        let effects = effects
+            .into_iter()
+            .map(|e| (enabled.contains(&e.kind()), e))
+            .collect();
+        Post {
+            output_format,
+            tonemap,
+            effects,
+            scene: target(device, "scene", HDR_FORMAT, size),
+            hdr: [
+                target(device, "post hdr", HDR_FORMAT, size),
+                target(device, "post hdr", HDR_FORMAT, size),
+            ],
+            display: [
+                target(device, "post display", output_format, size),
+                target(device, "post display", output_format, size),
+            ],
+        }
+    }
+
+    /// Turns an effect on or off, without rebuilding the chain
+    pub fn set_enabled(&mut self, kind: EffectKind, enabled: bool) {
+        for (on, e) in &mut self.effects {
+            if e.kind() == kind {
+                *on = enabled;
+            }
+        }
+    }
+
+    /// Returns the HDR target into which the scene should be drawn
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.scene.1
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        self.scene = target(device, "scene", HDR_FORMAT, size);
+        for t in &mut self.hdr {
+            *t = target(device, "post hdr", HDR_FORMAT, size);
+        }
+        for t in &mut self.display {
+            *t = target(device, "post display", self.output_format, size);
+        }
+        for (_, e) in &mut self.effects {
+            e.resize(device, size);
+        }
+    }
+
+    /// Runs the enabled effects on the scene target, writing into `output`
+    pub fn draw(
+        &self,
+        ctx: &Context,
+        output: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let enabled = |stage| {
+            self.effects
+                .iter()
+                .filter(move |(on, e)| *on && e.stage() == stage)
+                .map(|(_, e)| e)
+        };
+
+        let mut input = &self.scene.1;
+        for (i, e) in enabled(Stage::Hdr).enumerate() {
+            let out = &self.hdr[i % 2].1;
+            e.draw(ctx, input, out, encoder);
+            input = out;
+        }
+
+        let display = enabled(Stage::Display).collect::<Vec<_>>();
+        if display.is_empty() {
+            self.tonemap.draw(ctx.device, input, output, encoder);
+            return;
+        }
+        self.tonemap
+            .draw(ctx.device, input, &self.display[0].1, encoder);
+        let mut input = &self.display[0].1;
+        for (i, e) in display.iter().enumerate() {
+            let out = if i + 1 == display.len() {
+                output
+            } else {
+                &self.display[(i + 1) % 2].1
+            };
+            e.draw(ctx, input, out, encoder);
+            input = out;
+        }
+    }
+}
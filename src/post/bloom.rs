@@ -0,0 +1,276 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use super::{Context, Effect, EffectKind, Stage};
+use crate::{tonemap::HDR_FORMAT, winit::dpi::PhysicalSize};
+
+/// Number of levels in the blur chain, starting at half resolution
+const BLOOM_MIPS: u32 = 6;
+
+/// Uniforms for `bloom.wgsl`, which must match `Locals` in the shader
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Locals {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    _padding: f32,
+}
+
+/// Blooms the brightest parts of the scene, which are mostly emissive
+/// surfaces (since emissive strength pushes them well above 1.0).  The bright
+/// pass is repeatedly downsampled, then upsampled and accumulated back up the
+/// chain, which gives a wide and cheap blur.
+pub struct Bloom {
+    uniform_buf: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    /// One view per level of the blur chain
+    mips: Vec<wgpu::TextureView>,
+    /// Bind groups which read each level, for the downsample and upsample
+    /// pipelines respectively
+    down_bind_groups: Vec<wgpu::BindGroup>,
+    up_bind_groups: Vec<wgpu::BindGroup>,
+
+    prefilter_pipeline: wgpu::RenderPipeline,
+    down_pipeline: wgpu::RenderPipeline,
+    up_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+}
+
+impl Bloom {
+    pub fn new(device: &wgpu::Device, size: PhysicalSize<u32>) -> Self {
+        let locals = Locals {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.15,
+            _padding: 0.0,
+        };
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Bloom Uniform Buffer"),
+            contents: bytemuck::bytes_of(&locals),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("bloom"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("bloom.wgsl"))),
+        });
+        let pipeline = |label, entry_point, blend| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: None,
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: HDR_FORMAT,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        let mut out = Bloom {
+            uniform_buf,
+            sampler,
+            mips: vec![],
+            down_bind_groups: vec![],
+            up_bind_groups: vec![],
+            prefilter_pipeline: pipeline("bloom prefilter", "fs_prefilter", None),
+            down_pipeline: pipeline("bloom downsample", "fs_downsample", None),
+            up_pipeline: pipeline(
+                "bloom upsample",
+                "fs_upsample",
+                Some(wgpu::BlendState {
+                    color: additive,
+                    alpha: additive,
+                }),
+            ),
+            composite_pipeline: pipeline("bloom composite", "fs_composite", None),
+        };
+        out.resize(device, size);
+        out
+    }
+
+    /// Runs a single full-screen pass into `view`
+    fn pass(
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+        pipeline: &wgpu::RenderPipeline,
+        bind_group: &wgpu::BindGroup,
+    ) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("bloom"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+impl Effect for Bloom {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Bloom
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Hdr
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        let width = (size.width / 2).max(1);
+        let height = (size.height / 2).max(1);
+        let mip_level_count = BLOOM_MIPS.min(32 - width.max(height).leading_zeros());
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("bloom"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        self.mips = (0..mip_level_count)
+            .map(|i| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    base_mip_level: i,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let bind_groups = |pipeline: &wgpu::RenderPipeline| {
+            self.mips
+                .iter()
+                .map(|view| {
+                    device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("bloom"),
+                        layout: &pipeline.get_bind_group_layout(0),
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::TextureView(view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: wgpu::BindingResource::Sampler(&self.sampler),
+                            },
+                        ],
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let down_bind_groups = bind_groups(&self.down_pipeline);
+        let up_bind_groups = bind_groups(&self.up_pipeline);
+        self.down_bind_groups = down_bind_groups;
+        self.up_bind_groups = up_bind_groups;
+    }
+
+    fn draw(
+        &self,
+        ctx: &Context,
+        input: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let with_locals = |pipeline: &wgpu::RenderPipeline, src, scene| {
+            let mut entries = vec![
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(src),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ];
+            if let Some(scene) = scene {
+                entries.push(wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(scene),
+                });
+            }
+            ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("bloom"),
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &entries,
+            })
+        };
+        let prefilter = with_locals(&self.prefilter_pipeline, input, None);
+        let composite = with_locals(&self.composite_pipeline, &self.mips[0], Some(input));
+
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+        Self::pass(
+            encoder,
+            &self.mips[0],
+            clear,
+            &self.prefilter_pipeline,
+            &prefilter,
+        );
+        for i in 1..self.mips.len() {
+            Self::pass(
+                encoder,
+                &self.mips[i],
+                clear,
+                &self.down_pipeline,
+                &self.down_bind_groups[i - 1],
+            );
+        }
+        for i in (1..self.mips.len()).rev() {
+            Self::pass(
+                encoder,
+                &self.mips[i - 1],
+                wgpu::LoadOp::Load,
+                &self.up_pipeline,
+                &self.up_bind_groups[i],
+            );
+        }
+        Self::pass(encoder, output, clear, &self.composite_pipeline, &composite);
+    }
+}
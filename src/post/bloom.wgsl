@@ -0,0 +1,83 @@
+struct Locals {
+    // Luminance above which non-emissive surfaces start to bloom
+    threshold: f32,
+    // Width of the soft transition around the threshold
+    knee: f32,
+    // Amount of bloom added back into the scene
+    intensity: f32,
+};
+@group(0) @binding(0)
+var<uniform> r_locals: Locals;
+@group(0) @binding(1)
+var r_src: texture_2d<f32>;
+@group(0) @binding(2)
+var r_sampler: sampler;
+@group(0) @binding(3)
+var r_scene: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// A single triangle which covers the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(in_vertex_index & 2u) * 2 - 1);
+    out.position = vec4<f32>(x, y, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y) * vec2<f32>(0.5, -0.5) + 0.5;
+    return out;
+}
+
+// Averages a 4x4 texel footprint with four bilinear taps
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(r_src));
+    var total = vec3<f32>(0.0);
+    total += textureSampleLevel(r_src, r_sampler, uv + texel * vec2<f32>(-1.0, -1.0), 0.0).rgb;
+    total += textureSampleLevel(r_src, r_sampler, uv + texel * vec2<f32>(1.0, -1.0), 0.0).rgb;
+    total += textureSampleLevel(r_src, r_sampler, uv + texel * vec2<f32>(-1.0, 1.0), 0.0).rgb;
+    total += textureSampleLevel(r_src, r_sampler, uv + texel * vec2<f32>(1.0, 1.0), 0.0).rgb;
+    return total * 0.25;
+}
+
+// Keeps only the brightest parts of the scene, with a soft knee so that
+// bloom fades in rather than popping
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = downsample(in.uv);
+    let brightness = max(color.r, max(color.g, color.b));
+    let knee = r_locals.threshold * r_locals.knee;
+    var soft = clamp(brightness - r_locals.threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 1e-4);
+    let contribution = max(soft, brightness - r_locals.threshold) / max(brightness, 1e-4);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.uv), 1.0);
+}
+
+// 3x3 tent filter, additively blended into the next larger level
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(r_src));
+    var total = vec3<f32>(0.0);
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let w = f32((2 - abs(x)) * (2 - abs(y)));
+            let uv = in.uv + texel * vec2<f32>(f32(x), f32(y));
+            total += textureSampleLevel(r_src, r_sampler, uv, 0.0).rgb * w;
+        }
+    }
+    return vec4<f32>(total / 16.0, 1.0);
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureLoad(r_scene, vec2<i32>(in.position.xy), 0);
+    let bloom = textureSampleLevel(r_src, r_sampler, in.uv, 0.0).rgb;
+    return vec4<f32>(scene.rgb + bloom * r_locals.intensity, scene.a);
+}
@@ -0,0 +1,120 @@
+use std::borrow::Cow;
+
+use wgpu::util::DeviceExt;
+
+use super::{Context, Effect, EffectKind, Stage};
+use crate::winit::dpi::PhysicalSize;
+
+/// Fast approximate anti-aliasing, run on tone-mapped colors as a cheap
+/// alternative to MSAA
+pub struct Fxaa {
+    uniform_buf: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Fxaa {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        // Must match `Locals` in the shader (padded to 16 bytes)
+        let locals: [u32; 4] = [format.is_srgb() as u32, 0, 0, 0];
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("FXAA Uniform Buffer"),
+            contents: bytemuck::cast_slice(&locals),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("fxaa"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("fxaa.wgsl"))),
+        });
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("fxaa"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Fxaa {
+            uniform_buf,
+            sampler,
+            render_pipeline,
+        }
+    }
+}
+
+impl Effect for Fxaa {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Fxaa
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Display
+    }
+
+    fn resize(&mut self, _device: &wgpu::Device, _size: PhysicalSize<u32>) {
+        // Nothing depends on the window size
+    }
+
+    fn draw(
+        &self,
+        ctx: &Context,
+        input: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("fxaa"),
+            layout: &self.render_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(input),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("fxaa"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,69 @@
+struct Locals {
+    // Non-zero if the input texture decodes to linear values (i.e. it's an
+    // sRGB format), in which case luma is estimated perceptually
+    linear: u32,
+};
+@group(0) @binding(0)
+var<uniform> r_locals: Locals;
+@group(0) @binding(1)
+var r_color: texture_2d<f32>;
+@group(0) @binding(2)
+var r_sampler: sampler;
+
+const REDUCE_MIN: f32 = 1.0 / 128.0;
+const REDUCE_MUL: f32 = 1.0 / 8.0;
+const SPAN_MAX: f32 = 8.0;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// A single triangle which covers the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(in_vertex_index & 2u) * 2 - 1);
+    out.position = vec4<f32>(x, y, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y) * vec2<f32>(0.5, -0.5) + 0.5;
+    return out;
+}
+
+fn luma(c: vec3<f32>) -> f32 {
+    let l = dot(c, vec3<f32>(0.299, 0.587, 0.114));
+    return select(l, sqrt(l), r_locals.linear != 0u);
+}
+
+fn tap(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(r_color, r_sampler, uv, 0.0).rgb;
+}
+
+// Timothy Lottes' original FXAA, which blurs along the edge direction found
+// from the luma gradient of the four diagonal neighbours
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(r_color));
+    let rgb_m = tap(in.uv);
+    let nw = luma(tap(in.uv + vec2<f32>(-1.0, -1.0) * texel));
+    let ne = luma(tap(in.uv + vec2<f32>(1.0, -1.0) * texel));
+    let sw = luma(tap(in.uv + vec2<f32>(-1.0, 1.0) * texel));
+    let se = luma(tap(in.uv + vec2<f32>(1.0, 1.0) * texel));
+    let m = luma(rgb_m);
+
+    let luma_min = min(m, min(min(nw, ne), min(sw, se)));
+    let luma_max = max(m, max(max(nw, ne), max(sw, se)));
+
+    var dir = vec2<f32>(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+    let reduce = max((nw + ne + sw + se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    let scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX)) * texel;
+
+    let rgb_a = 0.5 * (tap(in.uv + dir * (1.0 / 3.0 - 0.5)) + tap(in.uv + dir * (2.0 / 3.0 - 0.5)));
+    let rgb_b = rgb_a * 0.5 + 0.25 * (tap(in.uv - dir * 0.5) + tap(in.uv + dir * 0.5));
+    let luma_b = luma(rgb_b);
+    if (luma_b < luma_min || luma_b > luma_max) {
+        return vec4<f32>(rgb_a, 1.0);
+    }
+    return vec4<f32>(rgb_b, 1.0);
+}
@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+
+use super::{target, Context, Effect, EffectKind, Stage};
+use crate::{tonemap::HDR_FORMAT, winit::dpi::PhysicalSize};
+
+const AO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+/// Uniforms for `ssao.wgsl`, which must match `Locals` in the shader
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Locals {
+    mat: [[f32; 4]; 4],
+    inv_mat: [[f32; 4]; 4],
+    radius: f32,
+    intensity: f32,
+    _padding: [f32; 2],
+}
+
+/// Screen-space ambient occlusion, reconstructed from the depth buffer and
+/// multiplied into the scene's HDR colors
+pub struct Ssao {
+    uniform_buf: wgpu::Buffer,
+    ao: (wgpu::Texture, wgpu::TextureView),
+    ao_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+}
+
+impl Ssao {
+    pub fn new(device: &wgpu::Device, size: PhysicalSize<u32>) -> Self {
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SSAO Uniform Buffer"),
+            size: std::mem::size_of::<Locals>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("ssao.wgsl"))),
+        });
+        let pipeline = |label, entry_point, format: wgpu::TextureFormat| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: None,
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(format.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        Ssao {
+            uniform_buf,
+            ao: target(device, "ssao", AO_FORMAT, size),
+            ao_pipeline: pipeline("ssao", "fs_ao", AO_FORMAT),
+            composite_pipeline: pipeline("ssao composite", "fs_composite", HDR_FORMAT),
+        }
+    }
+}
+
+impl Effect for Ssao {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Ssao
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Hdr
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        self.ao = target(device, "ssao", AO_FORMAT, size);
+    }
+
+    fn draw(
+        &self,
+        ctx: &Context,
+        input: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let locals = Locals {
+            mat: ctx.camera.mat().into(),
+            inv_mat: ctx.camera.mat_i().into(),
+            radius: 0.04,
+            intensity: 0.8,
+            _padding: [0.0; 2],
+        };
+        ctx.queue
+            .write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&locals));
+
+        let ao_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ssao"),
+            layout: &self.ao_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(ctx.depth),
+                },
+            ],
+        });
+        let composite_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ssao composite"),
+            layout: &self.composite_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(ctx.depth),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&self.ao.1),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(input),
+                },
+            ],
+        });
+
+        let passes = [
+            (&self.ao.1, &self.ao_pipeline, &ao_bind_group),
+            (output, &self.composite_pipeline, &composite_bind_group),
+        ];
+        for (view, pipeline, bind_group) in passes {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("ssao"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+struct Locals {
+    // World to clip space
+    mat: mat4x4<f32>,
+    // Clip to world space
+    inv_mat: mat4x4<f32>,
+    // Sampling radius, in clip-space X units
+    radius: f32,
+    // Strength of the darkening, from 0 to 1
+    intensity: f32,
+};
+@group(0) @binding(0)
+var<uniform> r_locals: Locals;
+@group(0) @binding(1)
+var r_depth: texture_depth_2d;
+@group(0) @binding(2)
+var r_ao: texture_2d<f32>;
+@group(0) @binding(3)
+var r_color: texture_2d<f32>;
+
+const SAMPLES: u32 = 16u;
+
+// A single triangle which covers the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(in_vertex_index & 2u) * 2 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+// Reconstructs the world-space position at the given pixel
+fn world_pos(p: vec2<i32>) -> vec3<f32> {
+    let dims = vec2<f32>(textureDimensions(r_depth));
+    let d = textureLoad(r_depth, p, 0);
+    let ndc = (vec2<f32>(p) + 0.5) / dims * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    let w = r_locals.inv_mat * vec4<f32>(ndc, d, 1.0);
+    return w.xyz / w.w;
+}
+
+// Jorge Jimenez's interleaved gradient noise, used to rotate the sample
+// pattern per pixel (the blur in fs_composite hides the resulting pattern)
+fn noise(p: vec2<f32>) -> f32 {
+    return fract(52.9829189 * fract(dot(p, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+@fragment
+fn fs_ao(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let p = vec2<i32>(position.xy);
+    let dims = vec2<i32>(textureDimensions(r_depth));
+    // Nothing was drawn here, so leave the backdrop alone
+    if (textureLoad(r_depth, p, 0) <= 0.0) {
+        return vec4<f32>(1.0);
+    }
+
+    // Build the normal from whichever neighbours are closest, which avoids
+    // smearing across silhouette edges
+    let pos = world_pos(p);
+    let l = world_pos(max(p - vec2<i32>(1, 0), vec2<i32>(0))) - pos;
+    let r = world_pos(min(p + vec2<i32>(1, 0), dims - 1)) - pos;
+    let u = world_pos(max(p - vec2<i32>(0, 1), vec2<i32>(0))) - pos;
+    let d = world_pos(min(p + vec2<i32>(0, 1), dims - 1)) - pos;
+    let dx = select(-l, r, dot(r, r) < dot(l, l));
+    let dy = select(-u, d, dot(d, d) < dot(u, u));
+    let eye = normalize((r_locals.inv_mat * vec4<f32>(0.0, 0.0, 1.0, 0.0)).xyz);
+    var n = normalize(cross(dx, dy));
+    if (dot(n, eye) < 0.0) {
+        n = -n;
+    }
+
+    let radius = length((r_locals.inv_mat * vec4<f32>(r_locals.radius, 0.0, 0.0, 0.0)).xyz);
+    let up = select(vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(1.0, 0.0, 0.0), abs(n.y) > 0.99);
+    let t = normalize(cross(up, n));
+    let b = cross(n, t);
+    let angle = noise(position.xy) * 6.2831853;
+
+    var occlusion = 0.0;
+    for (var i = 0u; i < SAMPLES; i++) {
+        // Spiral over the hemisphere, with more samples close to the center
+        let h = (f32(i) + 0.5) / f32(SAMPLES);
+        let phi = f32(i) * 2.3999632 + angle;
+        let cos_t = 1.0 - h;
+        let sin_t = sqrt(1.0 - cos_t * cos_t);
+        let dir = t * cos(phi) * sin_t + b * sin(phi) * sin_t + n * cos_t;
+        let s = pos + dir * radius * mix(0.1, 1.0, h * h);
+
+        let clip = r_locals.mat * vec4<f32>(s, 1.0);
+        let ndc = clip.xy / clip.w;
+        let q = vec2<i32>((ndc * vec2<f32>(0.5, -0.5) + 0.5) * vec2<f32>(dims));
+        if (any(q < vec2<i32>(0)) || any(q >= dims)) {
+            continue;
+        }
+        let scene = world_pos(q);
+        // Occluded if the visible surface is in front of the sample, fading
+        // out for surfaces which are far away from this pixel
+        let in_front = dot(scene - s, eye) > radius * 0.025;
+        let range = smoothstep(0.0, 1.0, radius / length(scene - pos));
+        occlusion += select(0.0, range, in_front);
+    }
+    let ao = 1.0 - occlusion / f32(SAMPLES) * r_locals.intensity;
+    return vec4<f32>(ao, ao, ao, 1.0);
+}
+
+// Applies a 4x4 box blur to the occlusion and darkens the scene with it
+@fragment
+fn fs_composite(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let p = vec2<i32>(position.xy);
+    let color = textureLoad(r_color, p, 0);
+    // Don't let occlusion bleed onto the backdrop
+    if (textureLoad(r_depth, p, 0) <= 0.0) {
+        return color;
+    }
+    let dims = vec2<i32>(textureDimensions(r_ao));
+    var ao = 0.0;
+    for (var y = -2; y < 2; y++) {
+        for (var x = -2; x < 2; x++) {
+            let q = clamp(p + vec2<i32>(x, y), vec2<i32>(0), dims - 1);
+            ao += textureLoad(r_ao, q, 0).r;
+        }
+    }
+    return vec4<f32>(color.rgb * ao / 16.0, color.a);
+}
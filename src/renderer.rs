@@ -0,0 +1,416 @@
+use std::path::PathBuf;
+
+use crate::winit::dpi::PhysicalSize;
+
+use crate::{
+    backdrop::{Backdrop, BackdropStyle},
+    bounds::Aabb,
+    camera::Camera,
+    environment::Environment,
+    ground::{Ground, GroundOptions, GroundStyle},
+    model::{Model, ViewMode},
+    outline::Outline,
+    overlay::{Overlay, OverlayKind},
+    post::{self, EffectKind, Post},
+    shadow::Shadow,
+    texture::{Image, Texture},
+    tonemap::{Tonemap, ToneMapping, HDR_FORMAT},
+};
+
+/// Format of the scene's depth buffer.  The stencil aspect marks the
+/// selected primitive.
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
+/// Settings which control how a scene is drawn
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    pub backdrop: BackdropStyle,
+
+    /// Equirectangular HDR map used for image-based lighting
+    pub environment: Option<PathBuf>,
+
+    /// Whether the key light casts shadows
+    pub shadows: bool,
+
+    pub ground: GroundOptions,
+
+    pub tone_mapping: ToneMapping,
+
+    /// Exposure adjustment in stops, applied before tone mapping
+    pub exposure: f32,
+
+    /// Post-processing effects to enable (they always run in a fixed order)
+    pub effects: Vec<EffectKind>,
+
+    pub view_mode: ViewMode,
+
+    pub overlays: Vec<OverlayKind>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            backdrop: BackdropStyle::default(),
+            environment: None,
+            shadows: true,
+            ground: GroundOptions {
+                style: GroundStyle::Off,
+                reflection: false,
+            },
+            tone_mapping: ToneMapping::Neutral,
+            exposure: 0.0,
+            effects: vec![EffectKind::Bloom, EffectKind::Fxaa],
+            view_mode: ViewMode::Shaded,
+            overlays: vec![],
+        }
+    }
+}
+
+/// A texture view for the renderer to draw into, e.g. a swapchain frame, an
+/// editor panel or an offscreen texture.  The format and size are those of the
+/// view's texture, which must allow `RENDER_ATTACHMENT` usage.
+#[derive(Copy, Clone)]
+pub struct Target<'a> {
+    pub view: &'a wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    pub size: PhysicalSize<u32>,
+}
+
+impl<'a> Target<'a> {
+    /// Describes a view of the whole of a texture
+    pub fn new(texture: &wgpu::Texture, view: &'a wgpu::TextureView) -> Self {
+        Target {
+            view,
+            format: texture.format(),
+            size: PhysicalSize::new(texture.width(), texture.height()),
+        }
+    }
+}
+
+/// Draws a glTF scene, with its backdrop, ground, overlays and
+/// post-processing, into a view owned by the caller.  The device, queue and
+/// camera are also the caller's, so a renderer can be embedded in any wgpu
+/// application.
+pub struct Renderer {
+    model: Model,
+    backdrop: Backdrop,
+    environment: Environment,
+    shadow: Shadow,
+    ground: Ground,
+    overlay: Overlay,
+    outline: Outline,
+    post: Post,
+    depth: (wgpu::Texture, wgpu::TextureView),
+    /// Bounds of the whole scene
+    bounds: Aabb,
+
+    /// Format and size of the targets which the intermediate textures and
+    /// output pipelines currently match
+    format: wgpu::TextureFormat,
+    size: PhysicalSize<u32>,
+    /// Kept for rebuilding the post-processing chain for a new format
+    options: RenderOptions,
+    /// Images named by the options, kept for rebuilding on a new device
+    images: Images,
+}
+
+/// The backdrop image and environment map named by the render options.  An
+/// `env:` backdrop is usually the environment map too, in which case it's
+/// only read and uploaded once.
+struct Images {
+    backdrop: Option<Image>,
+    environment: Option<Image>,
+    /// Whether the backdrop is also the environment map
+    shared: bool,
+}
+
+impl Images {
+    fn load(options: &RenderOptions) -> Result<Self, String> {
+        let backdrop = match &options.backdrop {
+            BackdropStyle::Image(path) => Some(Image::load_ldr(path)?),
+            BackdropStyle::Environment(path) => Some(Image::load_hdr(path)?),
+            _ => None,
+        };
+        let shared = match (&options.backdrop, &options.environment) {
+            (BackdropStyle::Environment(a), Some(b)) => a == b,
+            _ => false,
+        };
+        let environment = match &options.environment {
+            Some(path) if !shared => Some(Image::load_hdr(path)?),
+            _ => None,
+        };
+        Ok(Images {
+            backdrop,
+            environment,
+            shared,
+        })
+    }
+}
+
+impl Renderer {
+    /// Creates the GPU resources for a scene.  The format and size are those
+    /// of the targets it will usually be drawn into, although drawing into
+    /// others works too, at the cost of rebuilding some resources.  Fails if
+    /// the scene has nothing to draw, or an image named by the options can't
+    /// be read.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        gltf: &gltf::Gltf,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+        options: &RenderOptions,
+    ) -> Result<Self, String> {
+        let images = Images::load(options)?;
+        let depth = Self::create_depth(device, size);
+        let post = Self::create_post(device, format, size, options);
+        let (backdrop, environment) = Self::create_lighting(device, queue, options, &images);
+        let mut shadow = Shadow::new(device, options.shadows);
+        let mut model = Model::new(device, HDR_FORMAT, gltf, &environment, &shadow)?;
+        model.set_view_mode(options.view_mode);
+        let bounds = Aabb::from_verts(model.vertices());
+        shadow.fit(&bounds);
+
+        let ground = Self::create_ground(device, queue, &model, &shadow, &bounds, size, options);
+        let overlay = Overlay::new(
+            device,
+            HDR_FORMAT,
+            &bounds,
+            model.node_bounds(),
+            &options.overlays,
+        );
+        let outline = Outline::new(device, HDR_FORMAT);
+
+        Ok(Renderer {
+            model,
+            backdrop,
+            environment,
+            shadow,
+            ground,
+            overlay,
+            outline,
+            post,
+            depth,
+            bounds,
+            format,
+            size,
+            options: options.clone(),
+            images,
+        })
+    }
+
+    /// Recreates every GPU resource on a new device, e.g. after the old one
+    /// was lost.  The scene comes from the model's CPU-side copy, so the glTF
+    /// file isn't needed again.
+    pub fn rebuild(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let options = &self.options;
+        self.depth = Self::create_depth(device, self.size);
+        self.post = Self::create_post(device, self.format, self.size, options);
+        (self.backdrop, self.environment) =
+            Self::create_lighting(device, queue, options, &self.images);
+        self.shadow = Shadow::new(device, options.shadows);
+        self.shadow.fit(&self.bounds);
+        self.model
+            .rebuild(device, HDR_FORMAT, &self.environment, &self.shadow);
+        self.ground = Self::create_ground(
+            device,
+            queue,
+            &self.model,
+            &self.shadow,
+            &self.bounds,
+            self.size,
+            options,
+        );
+        self.overlay = Overlay::new(
+            device,
+            HDR_FORMAT,
+            &self.bounds,
+            self.model.node_bounds(),
+            &options.overlays,
+        );
+        self.outline = Outline::new(device, HDR_FORMAT);
+    }
+
+    /// Uploads the images and builds the backdrop and environment lighting
+    /// from them
+    fn create_lighting(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        options: &RenderOptions,
+        images: &Images,
+    ) -> (Backdrop, Environment) {
+        let upload = |image: &Option<Image>| {
+            image
+                .as_ref()
+                .map(|image| Texture::from_image(device, queue, image))
+        };
+        let backdrop_texture = upload(&images.backdrop);
+        let backdrop = Backdrop::new(
+            device,
+            queue,
+            HDR_FORMAT,
+            &options.backdrop,
+            backdrop_texture.as_ref(),
+        );
+        let environment_texture = upload(&images.environment);
+        let environment_texture = if images.shared {
+            backdrop_texture.as_ref()
+        } else {
+            environment_texture.as_ref()
+        };
+        let environment = Environment::new(device, queue, environment_texture);
+        (backdrop, environment)
+    }
+
+    fn create_ground(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        model: &Model,
+        shadow: &Shadow,
+        bounds: &Aabb,
+        size: PhysicalSize<u32>,
+        options: &RenderOptions,
+    ) -> Ground {
+        let mut ground = Ground::new(device, queue, HDR_FORMAT, shadow, bounds, options.ground);
+        ground.bake_contact(device, queue, model);
+        ground.resize(device, size);
+        ground
+    }
+
+    fn create_post(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+        options: &RenderOptions,
+    ) -> Post {
+        let tonemap = Tonemap::new(device, format, options.tone_mapping, options.exposure);
+        Post::new(device, format, size, tonemap, &options.effects)
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    pub fn model_mut(&mut self) -> &mut Model {
+        &mut self.model
+    }
+
+    /// Returns whether the key light casts shadows
+    pub fn shadows(&self) -> bool {
+        self.options.shadows
+    }
+
+    /// Turns the key light's shadows on or off.  While they're off, the
+    /// shadow map isn't drawn or sampled.
+    pub fn set_shadows(&mut self, shadows: bool) {
+        self.options.shadows = shadows;
+        self.shadow.set_enabled(shadows);
+    }
+
+    /// Returns whether a post-processing effect is enabled
+    pub fn effect(&self, kind: EffectKind) -> bool {
+        self.options.effects.contains(&kind)
+    }
+
+    /// Turns a post-processing effect on or off
+    pub fn set_effect(&mut self, kind: EffectKind, enabled: bool) {
+        self.options.effects.retain(|k| *k != kind);
+        if enabled {
+            self.options.effects.push(kind);
+        }
+        self.post.set_enabled(kind, enabled);
+    }
+
+    /// Returns the bounds of the whole scene, e.g. for fitting a camera
+    pub fn bounds(&self) -> &Aabb {
+        &self.bounds
+    }
+
+    /// Resizes the intermediate targets to match a new output size.  Drawing
+    /// does this anyway when the target's size changes.
+    pub fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        self.size = size;
+        self.depth = Self::create_depth(device, size);
+        self.ground.resize(device, size);
+        self.post.resize(device, size);
+    }
+
+    fn create_depth(
+        device: &wgpu::Device,
+        size: PhysicalSize<u32>,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let size = wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        };
+        let desc = wgpu::TextureDescriptor {
+            label: Some("depth tex"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            // Sampled by post-processing effects such as SSAO, and by the
+            // selection outline
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        };
+        let tex = device.create_texture(&desc);
+        let view = tex.create_view(&wgpu::TextureViewDescriptor::default());
+        (tex, view)
+    }
+
+    /// Records a frame of the scene, as seen by the camera, into the encoder.
+    /// The camera's size should match the target's, so the aspect is right.
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        target: &Target,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        if target.format != self.format {
+            self.format = target.format;
+            self.post = Self::create_post(device, self.format, target.size, &self.options);
+        }
+        if target.size != self.size {
+            self.resize(device, target.size);
+        }
+
+        let hdr = self.post.scene_view();
+
+        self.shadow.draw(&self.model, queue, encoder);
+        let mirror_height = self.ground.mirror_height();
+        self.model.update(camera, queue, mirror_height);
+        if let Some((color, depth)) = self.ground.reflection_target() {
+            self.model
+                .draw_mirrored(&self.environment, &self.shadow, color, depth, encoder);
+        }
+        self.backdrop.draw(camera, queue, hdr, &self.depth.1, encoder);
+        self.ground
+            .draw(camera, queue, &self.shadow, hdr, &self.depth.1, encoder);
+        self.model
+            .draw(&self.environment, &self.shadow, hdr, &self.depth.1, encoder);
+        self.overlay.draw(camera, queue, hdr, &self.depth.1, encoder);
+        if self.model.selected().is_some() {
+            let stencil = self.depth.0.create_view(&wgpu::TextureViewDescriptor {
+                aspect: wgpu::TextureAspect::StencilOnly,
+                ..Default::default()
+            });
+            self.outline.draw(device, &stencil, hdr, encoder);
+        }
+        let depth = self.depth.0.create_view(&wgpu::TextureViewDescriptor {
+            aspect: wgpu::TextureAspect::DepthOnly,
+            ..Default::default()
+        });
+        let ctx = post::Context {
+            device,
+            queue,
+            camera,
+            depth: &depth,
+        };
+        self.post.draw(&ctx, target.view, encoder);
+    }
+}
@@ -0,0 +1,85 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use glb_to_webgpu::Instant;
+
+/// When the viewer draws frames
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RedrawMode {
+    /// Only after something changes, and while the view is moving
+    OnDemand,
+    /// All the time, e.g. for measuring frame times
+    Continuous,
+}
+
+impl FromStr for RedrawMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "on-demand" => Ok(RedrawMode::OnDemand),
+            "continuous" => Ok(RedrawMode::Continuous),
+            _ => Err(format!(
+                "Invalid redraw mode '{}', expected on-demand or continuous",
+                s
+            )),
+        }
+    }
+}
+
+/// Lowest frame-rate cap, so that the view never goes more than ten seconds
+/// without a frame
+pub const MIN_FPS: f32 = 0.1;
+
+/// Decides when the next frame is due.  While idle, frames are only drawn
+/// when something asks for one (input, a resize, ...), but while animating
+/// they follow each other as fast as the frame-rate cap allows.
+pub struct Scheduler {
+    mode: RedrawMode,
+    /// Shortest time between frames, or zero if uncapped
+    interval: Duration,
+    last_frame: Option<Instant>,
+    /// Set when something has changed since the last frame
+    requested: bool,
+    /// Set when the last frame was part of an animation, so another follows
+    animating: bool,
+}
+
+impl Scheduler {
+    pub fn new(mode: RedrawMode, max_fps: Option<f32>) -> Self {
+        Scheduler {
+            mode,
+            interval: max_fps
+                .and_then(|fps| Duration::try_from_secs_f32(1.0 / fps).ok())
+                .unwrap_or_default(),
+            last_frame: None,
+            requested: true,
+            animating: false,
+        }
+    }
+
+    /// Asks for a frame, since something on screen has changed
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Notes that a frame was just drawn, and whether the view is still
+    /// moving
+    pub fn frame_drawn(&mut self, animating: bool) {
+        self.last_frame = Some(Instant::now());
+        self.requested = false;
+        self.animating = animating || self.mode == RedrawMode::Continuous;
+    }
+
+    /// Returns when the next frame should be drawn, or `None` if there's
+    /// nothing to draw until something asks for a frame
+    pub fn next_frame(&self) -> Option<Instant> {
+        if !self.requested && !self.animating {
+            return None;
+        }
+        Some(match self.last_frame {
+            Some(t) => t + self.interval,
+            None => Instant::now(),
+        })
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use glb_to_webgpu::wgpu;
+use log::{error, info, warn};
+
+/// Copies a rendered frame back from the GPU and saves it as a PNG.  The
+/// texture must have been created with `COPY_SRC` usage, and must be in an
+/// 8-bit RGBA or BGRA format.
+pub fn save(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, path: &Path) {
+    let format = texture.format();
+    let bgra = match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        _ => {
+            warn!("Cannot save screenshots from {:?} surfaces", format);
+            return;
+        }
+    };
+
+    // Rows in the staging buffer must be padded to the copy alignment
+    let (width, height) = (texture.width(), texture.height());
+    let row_bytes = width * 4;
+    let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Screenshot Buffer"),
+        size: (padded_row_bytes * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("screenshot"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_bytes),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |r| {
+        let _ = sender.send(r);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    match receiver.try_recv() {
+        Ok(Ok(())) => (),
+        Ok(Err(e)) => {
+            error!("Failed to map screenshot buffer: {}", e);
+            return;
+        }
+        Err(_) => {
+            error!("Screenshot buffer wasn't mapped");
+            return;
+        }
+    }
+
+    let mut pixels = Vec::with_capacity((row_bytes * height) as usize);
+    for row in slice.get_mapped_range().chunks_exact(padded_row_bytes as usize) {
+        pixels.extend_from_slice(&row[..row_bytes as usize]);
+    }
+    buffer.unmap();
+    if bgra {
+        for p in pixels.chunks_exact_mut(4) {
+            p.swap(0, 2);
+        }
+    }
+
+    match image::save_buffer(path, &pixels, width, height, image::ColorType::Rgba8) {
+        Ok(()) => info!("Saved screenshot to {:?}", path),
+        Err(e) => error!("Failed to save screenshot {:?}: {}", path, e),
+    }
+}
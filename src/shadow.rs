@@ -0,0 +1,269 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use glm::{Mat4, Vec3};
+use nalgebra_glm as glm;
+
+use crate::{
+    bounds::Aabb,
+    model::{GPUVertex, Model},
+};
+
+const SHADOW_SIZE: u32 = 2048;
+const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Uniforms for the key light, which must match `Light` in `model.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct LightUniform {
+    /// Transforms world-space positions into the shadow map's clip space
+    view_proj: [[f32; 4]; 4],
+    /// World-space direction towards the light
+    direction: [f32; 4],
+    /// Light color, pre-multiplied by intensity
+    color: [f32; 4],
+    /// Non-zero if the shadow map should be sampled
+    shadows: u32,
+    _padding: [u32; 3],
+}
+
+/// A directional key light, with a shadow map whose orthographic frustum is
+/// fitted around the scene bounds
+pub struct Shadow {
+    /// World-space direction towards the light
+    direction: Vec3,
+    color: Vec3,
+    enabled: bool,
+    view_proj: Mat4,
+
+    uniform_buf: wgpu::Buffer,
+    depth_view: wgpu::TextureView,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    /// Binds just the light uniforms, since the depth pass can't also sample
+    /// the shadow map which it's rendering into
+    pass_bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Shadow {
+    pub fn new(device: &wgpu::Device, enabled: bool) -> Self {
+        // Up, over the viewer's left shoulder
+        let direction = Vec3::new(-0.4, 1.0, 0.6).normalize();
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shadow map"),
+            size: wgpu::Extent3d {
+                width: SHADOW_SIZE,
+                height: SHADOW_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let depth_view = texture.create_view(&Default::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light Uniform Buffer"),
+            size: std::mem::size_of::<LightUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(
+                    std::mem::size_of::<LightUniform>() as u64,
+                ),
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("light"),
+            entries: &[
+                uniform_entry,
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("light"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let pass_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("shadow pass"),
+                entries: &[uniform_entry],
+            });
+        let pass_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shadow pass"),
+            layout: &pass_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shadow.wgsl"))),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&pass_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // Depth-only pass, so there's no fragment stage
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("shadow"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[GPUVertex::desc()],
+            },
+            fragment: None,
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: SHADOW_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Shadow {
+            direction,
+            color: Vec3::new(1.0, 0.96, 0.9) * 2.0,
+            enabled,
+            view_proj: Mat4::identity(),
+            uniform_buf,
+            depth_view,
+            bind_group_layout,
+            bind_group,
+            pass_bind_group,
+            render_pipeline,
+        }
+    }
+
+    /// Fits the light's orthographic frustum tightly around the scene, so
+    /// that the whole model casts shadows onto itself (and the ground)
+    pub fn fit(&mut self, bounds: &Aabb) {
+        // Pad the radius a little, so that a ground plane just below the
+        // model still fits within the frustum
+        let radius = bounds.radius().max(1e-6) * 1.5;
+        let center = bounds.center();
+        let eye = center + self.direction * radius * 2.0;
+        let up = if self.direction.y.abs() > 0.99 {
+            Vec3::z()
+        } else {
+            Vec3::y()
+        };
+        let view = glm::look_at_rh(&eye, &center, &up);
+        let proj = glm::ortho_rh_zo(-radius, radius, -radius, radius, 0.0, radius * 4.0);
+        self.view_proj = proj * view;
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Updates the light uniforms and, if shadows are enabled, renders the
+    /// model's depth from the light's point of view
+    pub fn draw(&self, model: &Model, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder) {
+        let uniform = LightUniform {
+            view_proj: self.view_proj.into(),
+            direction: [self.direction.x, self.direction.y, self.direction.z, 0.0],
+            color: [self.color.x, self.color.y, self.color.z, 1.0],
+            shadows: self.enabled as u32,
+            _padding: [0; 3],
+        };
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniform));
+        // The shaders skip the lookup, so the stale map is never seen
+        if !self.enabled {
+            return;
+        }
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("shadow"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, &self.pass_bind_group, &[]);
+        model.draw_geometry(&mut rpass);
+    }
+}
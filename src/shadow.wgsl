@@ -0,0 +1,13 @@
+struct Light {
+    view_proj: mat4x4<f32>,
+    direction: vec4<f32>,
+    color: vec4<f32>,
+    shadows: u32,
+};
+@group(0) @binding(0)
+var<uniform> r_light: Light;
+
+@vertex
+fn vs_main(@location(0) position: vec4<f32>) -> @builtin(position) vec4<f32> {
+    return r_light.view_proj * vec4<f32>(position.xyz, 1.0);
+}
@@ -0,0 +1,151 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use half::f16;
+use image::{ImageBuffer, Pixel, Rgba32FImage, RgbaImage, imageops::FilterType};
+use log::warn;
+use wgpu::util::DeviceExt;
+
+/// An image decoded from disk.  It's kept on the CPU, so that it can be
+/// uploaded again without reading the file, e.g. on a new device.
+pub enum Image {
+    /// Display-referred sRGB texels, from a PNG or JPEG
+    Ldr(RgbaImage),
+    /// Linear texels, from a Radiance `.hdr` or OpenEXR file
+    Hdr(Rgba32FImage),
+}
+
+impl Image {
+    /// Reads an LDR image (PNG, JPEG)
+    pub fn load_ldr(path: &Path) -> Result<Self, String> {
+        let img = image::open(path).map_err(|e| format!("Failed to load image {:?}: {}", path, e))?;
+        Ok(Image::Ldr(img.to_rgba8()))
+    }
+
+    /// Reads an HDR image (Radiance `.hdr` or OpenEXR)
+    pub fn load_hdr(path: &Path) -> Result<Self, String> {
+        let img = image::open(path)
+            .map_err(|e| format!("Failed to load HDR image {:?}: {}", path, e))?;
+        Ok(Image::Hdr(img.to_rgba32f()))
+    }
+}
+
+/// Scales an image down, keeping its aspect ratio, so that neither side is
+/// longer than `max_size`
+fn fit<P: Pixel + 'static>(
+    img: &ImageBuffer<P, Vec<P::Subpixel>>,
+    max_size: u32,
+) -> Cow<'_, ImageBuffer<P, Vec<P::Subpixel>>> {
+    let (width, height) = img.dimensions();
+    if width <= max_size && height <= max_size {
+        return Cow::Borrowed(img);
+    }
+    let scale = max_size as f32 / width.max(height) as f32;
+    let new_width = ((width as f32 * scale) as u32).clamp(1, max_size);
+    let new_height = ((height as f32 * scale) as u32).clamp(1, max_size);
+    warn!(
+        "Scaling a {}x{} image down to {}x{}, as the device only supports textures up to {} \
+         pixels across",
+        width, height, new_width, new_height, max_size
+    );
+    Cow::Owned(image::imageops::resize(img, new_width, new_height, FilterType::Triangle))
+}
+
+/// A sampled 2D texture, kept alive through its default view
+pub struct Texture {
+    pub view: wgpu::TextureView,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Texture {
+    /// Builds a 1x1 texture filled with a single RGBA8 color, used as a
+    /// placeholder when a bind group needs a texture but none is loaded
+    pub fn solid(device: &wgpu::Device, queue: &wgpu::Queue, rgba: [u8; 4]) -> Self {
+        Self::from_data(
+            device,
+            queue,
+            1,
+            1,
+            wgpu::TextureFormat::Rgba8Unorm,
+            &rgba,
+        )
+    }
+
+    /// Uploads an image as a texture, first scaling it down if it's larger
+    /// than the device allows
+    pub fn from_image(device: &wgpu::Device, queue: &wgpu::Queue, image: &Image) -> Self {
+        let max_size = device.limits().max_texture_dimension_2d;
+        match image {
+            Image::Ldr(img) => {
+                let img = fit(img, max_size);
+                Self::from_data(
+                    device,
+                    queue,
+                    img.width(),
+                    img.height(),
+                    wgpu::TextureFormat::Rgba8UnormSrgb,
+                    img.as_raw(),
+                )
+            }
+            Image::Hdr(img) => {
+                let img = fit(img, max_size);
+                Self::from_rgba_f32(device, queue, img.width(), img.height(), img.as_raw())
+            }
+        }
+    }
+
+    /// Builds a half-float texture from linear RGBA texels
+    pub fn from_rgba_f32(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        rgba: &[f32],
+    ) -> Self {
+        let texels = rgba.iter().map(|f| f16::from_f32(*f)).collect::<Vec<_>>();
+        Self::from_data(
+            device,
+            queue,
+            width,
+            height,
+            wgpu::TextureFormat::Rgba16Float,
+            bytemuck::cast_slice(&texels),
+        )
+    }
+
+    fn from_data(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        data: &[u8],
+    ) -> Self {
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            data,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Texture {
+            view,
+            width,
+            height,
+        }
+    }
+}
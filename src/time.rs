@@ -0,0 +1,60 @@
+//! A monotonic clock which also works in the WASI build, where
+//! `std::time::Instant` isn't available.
+
+#[cfg(not(target_arch="wasm32"))]
+pub use std::time::Instant;
+
+#[cfg(target_arch="wasm32")]
+pub use wasi_clock::Instant;
+
+/// Blocks until the given time, returning straight away if it has passed
+#[cfg(target_arch="wasm32")]
+pub fn sleep_until(deadline: Instant) {
+    wasi::clocks::monotonic_clock::subscribe_instant(deadline.nanos()).block();
+}
+
+#[cfg(target_arch="wasm32")]
+mod wasi_clock {
+    use std::ops::{Add, Sub};
+    use std::time::Duration;
+
+    /// A reading of the WASI monotonic clock, in nanoseconds, with the parts
+    /// of the `std::time::Instant` API which the viewer uses
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Instant(u64);
+
+    impl Instant {
+        pub fn now() -> Self {
+            Instant(wasi::clocks::monotonic_clock::now())
+        }
+
+        pub fn duration_since(&self, earlier: Instant) -> Duration {
+            Duration::from_nanos(self.0.saturating_sub(earlier.0))
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Self::now().duration_since(*self)
+        }
+
+        /// Returns the raw clock reading, for subscribing to the clock
+        pub(crate) fn nanos(&self) -> u64 {
+            self.0
+        }
+    }
+
+    impl Sub for Instant {
+        type Output = Duration;
+
+        fn sub(self, earlier: Instant) -> Duration {
+            self.duration_since(earlier)
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Instant;
+
+        fn add(self, duration: Duration) -> Instant {
+            Instant(self.0 + duration.as_nanos() as u64)
+        }
+    }
+}
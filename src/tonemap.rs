@@ -0,0 +1,182 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+/// Format of the intermediate target into which the scene is shaded
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Operator used to map HDR scene colors into displayable range
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// Filmic curve fitted to the ACES reference rendering transform
+    Aces = 0,
+    /// Blender's AgX, which desaturates bright colors more gracefully
+    Agx = 1,
+    /// Simple per-channel `x / (1 + x)`
+    Reinhard = 2,
+    /// Khronos PBR Neutral, which keeps base colors close to their sRGB values
+    Neutral = 3,
+}
+
+impl FromStr for ToneMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "aces" => Ok(ToneMapping::Aces),
+            "agx" => Ok(ToneMapping::Agx),
+            "reinhard" => Ok(ToneMapping::Reinhard),
+            "neutral" => Ok(ToneMapping::Neutral),
+            _ => Err(format!(
+                "Invalid tone mapping '{}', expected aces, agx, reinhard or neutral",
+                s
+            )),
+        }
+    }
+}
+
+/// Uniforms for `tonemap.wgsl`, which must match `Locals` in the shader
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Locals {
+    /// Linear scale applied before tone mapping
+    exposure: f32,
+    mapping: u32,
+    /// Non-zero if the output format isn't sRGB, so the shader must encode
+    encode_srgb: u32,
+    _padding: u32,
+}
+
+/// Resolves HDR scene colors into the final output format
+pub struct Tonemap {
+    uniform_buf: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Tonemap {
+    /// Builds a tone mapper writing to `output_format`.  `exposure` is given
+    /// in stops (EV), so zero leaves the scene unchanged.
+    pub fn new(
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        mapping: ToneMapping,
+        exposure: f32,
+    ) -> Self {
+        let locals = Locals {
+            exposure: exposure.exp2(),
+            mapping: mapping as u32,
+            encode_srgb: !output_format.is_srgb() as u32,
+            _padding: 0,
+        };
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tonemap Uniform Buffer"),
+            contents: bytemuck::bytes_of(&locals),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("tonemap"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<Locals>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("tonemap.wgsl"))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("tonemap"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(output_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Tonemap {
+            uniform_buf,
+            bind_group_layout,
+            render_pipeline,
+        }
+    }
+
+    /// Tone maps `input` into `output`, which must be in the output format
+    /// passed to `new`
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        input: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("tonemap"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(input),
+                },
+            ],
+        });
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("tonemap"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,129 @@
+struct Locals {
+    exposure: f32,
+    mapping: u32,
+    encode_srgb: u32,
+};
+@group(0) @binding(0)
+var<uniform> r_locals: Locals;
+@group(0) @binding(1)
+var r_hdr: texture_2d<f32>;
+
+// Must match ToneMapping in tonemap.rs
+const OP_ACES: u32 = 0u;
+const OP_AGX: u32 = 1u;
+const OP_REINHARD: u32 = 2u;
+
+// A single triangle which covers the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(in_vertex_index & 2u) * 2 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+// Stephen Hill's fit of the ACES RRT + ODT, including the sRGB <-> ACEScg
+// conversions.  The input is scaled so that mid-grey stays roughly in place.
+fn tonemap_aces(color: vec3<f32>) -> vec3<f32> {
+    let input = mat3x3<f32>(
+        0.59719, 0.07600, 0.02840,
+        0.35458, 0.90834, 0.13383,
+        0.04823, 0.01566, 0.83777,
+    );
+    let output = mat3x3<f32>(
+        1.60475, -0.10208, -0.00327,
+        -0.53108, 1.10813, -0.07276,
+        -0.07367, -0.00605, 1.07602,
+    );
+    let v = input * (color / 0.6);
+    let a = v * (v + 0.0245786) - 0.000090537;
+    let b = v * (0.983729 * v + 0.4329510) + 0.238081;
+    return clamp(output * (a / b), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// Polynomial approximation of the AgX base contrast curve
+fn agx_contrast(x: vec3<f32>) -> vec3<f32> {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2
+        - 40.14 * x4 * x
+        + 31.96 * x4
+        - 6.868 * x2 * x
+        + 0.4298 * x2
+        + 0.1191 * x
+        - 0.00232;
+}
+
+// Minimal AgX, after Benjamin Wrensch's fit of Troy Sobotka's transform
+fn tonemap_agx(color: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3<f32>(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+    );
+    let outset = mat3x3<f32>(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    var v = inset * color;
+    v = clamp(log2(max(v, vec3<f32>(1e-10))), vec3<f32>(min_ev), vec3<f32>(max_ev));
+    v = (v - min_ev) / (max_ev - min_ev);
+    v = outset * agx_contrast(v);
+    // The curve produces display-encoded values, so return to linear
+    return pow(max(v, vec3<f32>(0.0)), vec3<f32>(2.2));
+}
+
+fn tonemap_reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+// Khronos PBR Neutral, as specified in the glTF sample viewer
+fn tonemap_neutral(color: vec3<f32>) -> vec3<f32> {
+    let start_compression = 0.8 - 0.04;
+    let desaturation = 0.15;
+
+    let x = min(color.r, min(color.g, color.b));
+    let offset = select(0.04, x - 6.25 * x * x, x < 0.08);
+    var v = color - offset;
+
+    let peak = max(v.r, max(v.g, v.b));
+    if (peak < start_compression) {
+        return v;
+    }
+    let d = 1.0 - start_compression;
+    let new_peak = 1.0 - d * d / (peak + d - start_compression);
+    v *= new_peak / peak;
+
+    let g = 1.0 - 1.0 / (desaturation * (peak - new_peak) + 1.0);
+    return mix(v, vec3<f32>(new_peak), g);
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    let lo = c * 12.92;
+    let hi = 1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(hi, lo, c <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let hdr = textureLoad(r_hdr, vec2<i32>(position.xy), 0).rgb;
+    let color = max(hdr, vec3<f32>(0.0)) * r_locals.exposure;
+
+    var mapped: vec3<f32>;
+    switch r_locals.mapping {
+        case OP_ACES: { mapped = tonemap_aces(color); }
+        case OP_AGX: { mapped = tonemap_agx(color); }
+        case OP_REINHARD: { mapped = tonemap_reinhard(color); }
+        default: { mapped = tonemap_neutral(color); }
+    }
+    mapped = clamp(mapped, vec3<f32>(0.0), vec3<f32>(1.0));
+
+    // sRGB surfaces encode on write; otherwise we have to do it ourselves
+    if (r_locals.encode_srgb != 0u) {
+        mapped = linear_to_srgb(mapped);
+    }
+    return vec4<f32>(mapped, 1.0);
+}
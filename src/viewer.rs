@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use glb_to_webgpu::gltf::Gltf;
+use glb_to_webgpu::Instant;
+use glb_to_webgpu::wgpu;
+use glb_to_webgpu::winit::{
+    application::ApplicationHandler,
+    event::{DeviceEvent, DeviceId, MouseScrollDelta, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow},
+    window::{Window, WindowId},
+};
+use nalgebra_glm::Vec2;
+
+use crate::{adapter, app::App, options::Options, schedule::Scheduler};
+
+/// Runs the app on the event loop, the same way on every target.  The window,
+/// its surface and the GPU state are only created once the loop resumes, and
+/// the surface is dropped again whenever it suspends.
+pub struct Viewer {
+    options: Options,
+    instance: wgpu::Instance,
+    /// The model to show, until the app is created from it
+    gltf: Option<Gltf>,
+    window: Option<Arc<Window>>,
+    app: Option<App>,
+    scheduler: Scheduler,
+    /// Why the loop was stopped early, if it was
+    error: Option<String>,
+}
+
+impl Viewer {
+    pub fn new(gltf: Gltf, options: Options) -> Self {
+        Viewer {
+            instance: adapter::instance(&options.gpu),
+            scheduler: Scheduler::new(options.redraw, options.max_fps),
+            gltf: Some(gltf),
+            window: None,
+            app: None,
+            error: None,
+            options,
+        }
+    }
+
+    /// Returns the error which stopped the loop, if any
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error.take()
+    }
+
+    /// Creates the app, with a device on an adapter which can draw to the
+    /// surface
+    fn create_app(
+        &mut self,
+        surface: wgpu::Surface<'static>,
+        window: &Window,
+    ) -> Result<App, String> {
+        let adapter =
+            pollster::block_on(adapter::select(&self.instance, &surface, &self.options.gpu));
+        let (device, queue) = pollster::block_on(adapter.request_device(&Default::default(), None))
+            .map_err(|e| format!("Failed to create device: {}", e))?;
+        let gltf = self.gltf.take().unwrap();
+        App::new(
+            window.inner_size(),
+            adapter,
+            surface,
+            device,
+            queue,
+            gltf,
+            &self.options,
+        )
+    }
+}
+
+impl ApplicationHandler for Viewer {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let window = match &self.window {
+            Some(window) => Arc::clone(window),
+            None => {
+                let attributes = Window::default_attributes().with_title("glb-to-webgpu");
+                let window = event_loop
+                    .create_window(attributes)
+                    .expect("Failed to create window");
+                Arc::clone(self.window.insert(Arc::new(window)))
+            }
+        };
+        let surface = self
+            .instance
+            .create_surface(Arc::clone(&window))
+            .expect("Failed to create surface");
+        match &mut self.app {
+            Some(app) => app.resume(surface, window.inner_size()),
+            None => match self.create_app(surface, &window) {
+                Ok(app) => self.app = Some(app),
+                Err(e) => {
+                    self.error = Some(e);
+                    event_loop.exit();
+                    return;
+                }
+            },
+        }
+        self.scheduler.request();
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(app) = &mut self.app {
+            app.suspend();
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let Some(app) = &mut self.app else {
+            return;
+        };
+        let scheduler = &mut self.scheduler;
+        match event {
+            WindowEvent::CloseRequested => {
+                event_loop.exit();
+            }
+            WindowEvent::RedrawRequested => {
+                if let Err(e) = app.redraw() {
+                    self.error = Some(format!("Failed to draw a frame: {}", e));
+                    event_loop.exit();
+                    return;
+                }
+                scheduler.frame_drawn(app.is_animating());
+            }
+            WindowEvent::Focused(false) => app.release_keys(),
+            WindowEvent::Resized(size) => {
+                app.resize(size);
+                scheduler.request();
+            }
+            WindowEvent::MouseInput { button, state, .. } => {
+                app.mouse_input(button, state);
+                scheduler.request();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                app.cursor_moved(Vec2::new(position.x as f32, position.y as f32));
+                scheduler.request();
+            }
+            WindowEvent::Touch(touch) => {
+                let pos = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+                app.touch(touch.id, touch.phase, pos);
+                scheduler.request();
+            }
+            WindowEvent::PinchGesture { delta, .. } => {
+                app.pinch(delta as f32);
+                scheduler.request();
+            }
+            WindowEvent::RotationGesture { delta, .. } => {
+                app.rotation_gesture(delta);
+                scheduler.request();
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                if let MouseScrollDelta::LineDelta(_, verti) = delta {
+                    app.camera.mouse_scroll(verti * 10.0);
+                }
+                scheduler.request();
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                app.key_input(&event);
+                scheduler.request();
+            }
+            _ => {}
+        }
+    }
+
+    fn device_event(&mut self, _: &ActiveEventLoop, _: DeviceId, event: DeviceEvent) {
+        let Some(app) = &mut self.app else {
+            return;
+        };
+        if matches!(event, DeviceEvent::MouseWheel { .. }) {
+            self.scheduler.request();
+        }
+        app.device_event(event);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let (Some(window), Some(app)) = (&self.window, &self.app) else {
+            return;
+        };
+        // Nothing is drawn while suspended or minimized, so wait to be resumed
+        // or resized
+        if app.is_suspended() || app.is_minimized() {
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        }
+        // Keep trying to recreate a lost device, whether or not frames are due
+        if let Some(t) = app.device_retry() {
+            if t <= Instant::now() {
+                window.request_redraw();
+            } else {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(t));
+            }
+            return;
+        }
+        match self.scheduler.next_frame() {
+            Some(t) if t <= Instant::now() => window.request_redraw(),
+            Some(t) => event_loop.set_control_flow(ControlFlow::WaitUntil(t)),
+            None => event_loop.set_control_flow(ControlFlow::Wait),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! The command line and filesystem of the WASI component build, which come
+//! from the host through the WASI CLI and filesystem interfaces rather than
+//! through `std`.
+
+use std::path::{Component, Path};
+
+use wasi::cli::environment;
+use wasi::filesystem::{
+    preopens,
+    types::{Descriptor, DescriptorFlags, OpenFlags, PathFlags},
+};
+
+/// Largest read to ask the host for at once
+const CHUNK: u64 = 1 << 20;
+
+/// Returns the command line, including the program name
+pub fn args() -> Vec<String> {
+    environment::get_arguments()
+}
+
+/// Returns the value of an environment variable, if the host passed it
+pub fn var(name: &str) -> Option<String> {
+    environment::get_environment()
+        .into_iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value)
+}
+
+/// Reads a whole file from one of the directories the host preopened.
+/// Absolute paths are looked up under the preopen whose guest path they
+/// start with, and relative ones under the `.` preopen, if there is one, or
+/// else under each preopen in turn.
+pub fn read(path: &Path) -> Result<Vec<u8>, String> {
+    let dirs = preopens::get_directories();
+    if dirs.is_empty() {
+        return Err("the host didn't preopen any directories".to_string());
+    }
+
+    let mut candidates = vec![];
+    if path.is_absolute() {
+        // Prefer the most specific preopen, e.g. /data/models over /data
+        let mut matches = dirs
+            .iter()
+            .filter_map(|(dir, guest)| Some((dir, path.strip_prefix(guest).ok()?, guest.len())))
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|&(_, _, len)| std::cmp::Reverse(len));
+        candidates.extend(matches.into_iter().map(|(dir, rel, _)| (dir, rel)));
+    } else if let Some((dir, _)) = dirs.iter().find(|(_, guest)| is_current_dir(guest)) {
+        candidates.push((dir, path));
+    } else {
+        candidates.extend(dirs.iter().map(|(dir, _)| (dir, path)));
+    }
+
+    let mut errors = vec![];
+    for (dir, rel) in candidates {
+        match read_at(dir, rel) {
+            Ok(data) => return Ok(data),
+            Err(e) => errors.push(e),
+        }
+    }
+    if errors.is_empty() {
+        let names = dirs
+            .iter()
+            .map(|(_, guest)| guest.as_str())
+            .collect::<Vec<_>>();
+        Err(format!(
+            "it isn't in a preopened directory ({})",
+            names.join(", ")
+        ))
+    } else {
+        Err(errors.join(", "))
+    }
+}
+
+fn is_current_dir(guest: &str) -> bool {
+    Path::new(guest)
+        .components()
+        .all(|c| c == Component::CurDir)
+}
+
+fn read_at(dir: &Descriptor, path: &Path) -> Result<Vec<u8>, String> {
+    let path = path.to_str().ok_or("the path isn't valid UTF-8")?;
+    let file = dir
+        .open_at(
+            PathFlags::SYMLINK_FOLLOW,
+            path,
+            OpenFlags::empty(),
+            DescriptorFlags::READ,
+        )
+        .map_err(|e| format!("{:?}", e))?;
+    let size = file.stat().map_err(|e| format!("{:?}", e))?.size;
+
+    let mut data = Vec::with_capacity(size as usize);
+    loop {
+        let (chunk, end) = file
+            .read(CHUNK, data.len() as u64)
+            .map_err(|e| format!("{:?}", e))?;
+        data.extend_from_slice(&chunk);
+        if end || chunk.is_empty() {
+            return Ok(data);
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! A stand-in for the parts of [winit] 0.30 which the viewer uses, for the
+//! WASI build, where there's no windowing system for winit to talk to.
+//!
+//! The types and the event loop mirror winit's, so code written against one
+//! builds against the other.  Windows and their input come from a
+//! [`backend::Backend`]: the WASI surface in the component build, or a
+//! scripted [`backend::mock::Mock`] when testing the event loop natively.
+//!
+//! [winit]: https://docs.rs/winit/0.30
+
+pub mod application;
+pub mod backend;
+pub mod dpi;
+pub mod error;
+pub mod event;
+pub mod event_loop;
+pub mod keyboard;
+pub mod window;
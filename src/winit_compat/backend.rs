@@ -0,0 +1,59 @@
+//! Where windows and their input come from.  This isn't part of winit, which
+//! talks to each platform's windowing system directly.
+
+use wgpu::rwh::{HasDisplayHandle, HasWindowHandle};
+
+use crate::time::Instant;
+
+use super::{
+    dpi::PhysicalSize,
+    error::OsError,
+    event::{DeviceEvent, WindowEvent},
+    window::{WindowAttributes, WindowId},
+};
+
+pub mod mock;
+#[cfg(target_arch="wasm32")]
+pub mod wasi;
+
+/// Something which happened on the platform, for the event loop to pass on
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendEvent {
+    Window(WindowId, WindowEvent),
+    Device(DeviceEvent),
+    /// The application must stop rendering, e.g. because it was sent to the
+    /// background
+    Suspended,
+    /// The application may render again after being suspended
+    Resumed,
+}
+
+/// A platform for the event loop to run on
+pub trait Backend {
+    /// Creates the platform side of a window, whose events will carry the
+    /// given id
+    fn create_window(
+        &mut self,
+        id: WindowId,
+        attributes: &WindowAttributes,
+    ) -> Result<Box<dyn PlatformWindow>, OsError>;
+
+    /// Returns the next pending event, without blocking
+    fn next_event(&mut self) -> Option<BackendEvent>;
+
+    /// Blocks until an event may be pending, or until the deadline.  Waking
+    /// up early without any events is allowed.
+    fn wait(&mut self, deadline: Option<Instant>);
+}
+
+/// The platform side of a window, which a surface can be created from
+pub trait PlatformWindow: HasWindowHandle + HasDisplayHandle {
+    fn inner_size(&self) -> PhysicalSize<u32>;
+}
+
+/// The backend an [`EventLoop::new`](super::event_loop::EventLoop::new)
+/// runs on
+#[cfg(target_arch="wasm32")]
+pub(super) fn platform() -> Box<dyn Backend> {
+    Box::new(wasi::Wasi::new())
+}
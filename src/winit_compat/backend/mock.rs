@@ -0,0 +1,120 @@
+//! A backend which plays back scripted events, for testing code which runs
+//! on the event loop without a windowing system
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use wgpu::rwh::{DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle};
+
+use super::{Backend, BackendEvent, PlatformWindow};
+use crate::time::Instant;
+use crate::winit_compat::{
+    dpi::PhysicalSize,
+    error::OsError,
+    window::{WindowAttributes, WindowId},
+};
+
+/// Plays back events in batches: the first as soon as the loop starts, and
+/// each of the others after one of the loop's waits.  Clones share their
+/// script and record, so a test can keep one to check what the loop did.
+#[derive(Clone, Default)]
+pub struct Mock {
+    state: Rc<RefCell<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Events which are ready now
+    pending: VecDeque<BackendEvent>,
+    /// Batches of events for after each wait
+    batches: VecDeque<Vec<BackendEvent>>,
+    windows: Vec<(WindowId, String)>,
+    /// Deadlines the loop has waited for
+    waits: Vec<Option<Instant>>,
+    /// Set to make creating windows fail
+    failing: bool,
+}
+
+impl Mock {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Makes an event ready straight away
+    pub fn send(&self, event: BackendEvent) {
+        self.state.borrow_mut().pending.push_back(event);
+    }
+
+    /// Queues a batch of events for after the loop's next unanswered wait
+    pub fn send_after_wait(&self, events: Vec<BackendEvent>) {
+        self.state.borrow_mut().batches.push_back(events);
+    }
+
+    /// Makes creating windows fail from now on
+    pub fn fail_windows(&self) {
+        self.state.borrow_mut().failing = true;
+    }
+
+    /// Returns the id and title of each window created so far
+    pub fn windows(&self) -> Vec<(WindowId, String)> {
+        self.state.borrow().windows.clone()
+    }
+
+    /// Returns the deadline of each wait so far
+    pub fn waits(&self) -> Vec<Option<Instant>> {
+        self.state.borrow().waits.clone()
+    }
+}
+
+impl Backend for Mock {
+    fn create_window(
+        &mut self,
+        id: WindowId,
+        attributes: &WindowAttributes,
+    ) -> Result<Box<dyn PlatformWindow>, OsError> {
+        let mut state = self.state.borrow_mut();
+        if state.failing {
+            return Err(OsError::new("mock backend told to fail"));
+        }
+        state.windows.push((id, attributes.title.clone()));
+        Ok(Box::new(MockWindow))
+    }
+
+    fn next_event(&mut self) -> Option<BackendEvent> {
+        self.state.borrow_mut().pending.pop_front()
+    }
+
+    /// Makes the next batch ready.  Waiting with nothing left to play and no
+    /// deadline panics, since a real backend would block forever.
+    fn wait(&mut self, deadline: Option<Instant>) {
+        let mut state = self.state.borrow_mut();
+        state.waits.push(deadline);
+        match state.batches.pop_front() {
+            Some(batch) => state.pending.extend(batch),
+            None if deadline.is_none() => panic!("Event loop waited with no events left to play"),
+            None => (),
+        }
+    }
+}
+
+/// A window with nothing to draw to
+struct MockWindow;
+
+impl PlatformWindow for MockWindow {
+    fn inner_size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(800, 600)
+    }
+}
+
+impl HasWindowHandle for MockWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        Err(HandleError::NotSupported)
+    }
+}
+
+impl HasDisplayHandle for MockWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Err(HandleError::NotSupported)
+    }
+}
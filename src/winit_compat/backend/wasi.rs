@@ -0,0 +1,253 @@
+//! Windows backed by wasi-gfx surfaces, which the host shows however it
+//! likes, e.g. as a native window or a canvas.
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::Duration;
+
+use wasi::io::poll::{Pollable, poll};
+use wgpu::backend::wasi_webgpu::wasi::webgpu::{
+    graphics_context::Context,
+    surface::{self, CreateDesc, Surface},
+};
+use wgpu::rwh::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, RawDisplayHandle,
+    RawWindowHandle, WasiDisplayHandle, WasiWindowHandle, WindowHandle,
+};
+
+use super::{Backend, BackendEvent, PlatformWindow};
+use crate::time::Instant;
+use crate::winit_compat::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    error::OsError,
+    event::{DeviceId, ElementState, KeyEvent, MouseButton, WindowEvent},
+    keyboard::{Key, NamedKey},
+    window::{WindowAttributes, WindowId},
+};
+
+/// The backend of the component build
+#[derive(Default)]
+pub struct Wasi {
+    windows: Vec<SurfaceEvents>,
+}
+
+impl Wasi {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl Backend for Wasi {
+    fn create_window(
+        &mut self,
+        id: WindowId,
+        _attributes: &WindowAttributes,
+    ) -> Result<Box<dyn PlatformWindow>, OsError> {
+        let graphics_context = Context::new();
+        let surface = Surface::new(CreateDesc {
+            height: None,
+            width: None,
+        });
+        surface.connect_graphics_context(&graphics_context);
+        let surface = Rc::new(surface);
+        self.windows
+            .push(SurfaceEvents::new(id, Rc::clone(&surface)));
+        Ok(Box::new(SurfaceWindow {
+            surface,
+            graphics_context,
+        }))
+    }
+
+    fn next_event(&mut self) -> Option<BackendEvent> {
+        self.windows.iter_mut().find_map(|w| w.next_event())
+    }
+
+    fn wait(&mut self, deadline: Option<Instant>) {
+        if self.windows.is_empty() {
+            // Nothing will arrive without a window, so waiting without a
+            // deadline just checks back now and then
+            let deadline = deadline.unwrap_or_else(|| Instant::now() + Duration::from_millis(100));
+            crate::time::sleep_until(deadline);
+            return;
+        }
+        let timer = deadline.map(|d| wasi::clocks::monotonic_clock::subscribe_instant(d.nanos()));
+        let mut pollables = self
+            .windows
+            .iter()
+            .flat_map(|w| w.pollables())
+            .collect::<Vec<_>>();
+        pollables.extend(timer.as_ref());
+        poll(&pollables);
+    }
+}
+
+struct SurfaceWindow {
+    /// Shared with the window's event source, which polls it for input
+    surface: Rc<Surface>,
+    graphics_context: Context,
+}
+
+impl PlatformWindow for SurfaceWindow {
+    fn inner_size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.surface.width(), self.surface.height())
+    }
+}
+
+impl HasWindowHandle for SurfaceWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        let handle = self.graphics_context.handle();
+        let raw = RawWindowHandle::Wasi(WasiWindowHandle::new(handle));
+        Ok(unsafe { WindowHandle::borrow_raw(raw) })
+    }
+}
+
+impl HasDisplayHandle for SurfaceWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        let handle = self.graphics_context.handle();
+        let raw = RawDisplayHandle::Wasi(WasiDisplayHandle::new(handle));
+        Ok(unsafe { DisplayHandle::borrow_raw(raw) })
+    }
+}
+
+/// Input from a surface.  The surface reports resizes, pointer presses and
+/// movement, and keys; it has no wheel or close events, nor pointer buttons,
+/// so presses are reported as the left button.
+struct SurfaceEvents {
+    id: WindowId,
+    surface: Rc<Surface>,
+    resize: Pollable,
+    pointer_down: Pollable,
+    pointer_up: Pollable,
+    pointer_move: Pollable,
+    key_down: Pollable,
+    key_up: Pollable,
+    pending: VecDeque<WindowEvent>,
+}
+
+impl SurfaceEvents {
+    fn new(id: WindowId, surface: Rc<Surface>) -> Self {
+        SurfaceEvents {
+            id,
+            resize: surface.subscribe_resize(),
+            pointer_down: surface.subscribe_pointer_down(),
+            pointer_up: surface.subscribe_pointer_up(),
+            pointer_move: surface.subscribe_pointer_move(),
+            key_down: surface.subscribe_key_down(),
+            key_up: surface.subscribe_key_up(),
+            surface,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn pollables(&self) -> [&Pollable; 6] {
+        [
+            &self.resize,
+            &self.pointer_down,
+            &self.pointer_up,
+            &self.pointer_move,
+            &self.key_down,
+            &self.key_up,
+        ]
+    }
+
+    fn next_event(&mut self) -> Option<BackendEvent> {
+        if self.pending.is_empty() {
+            self.collect();
+        }
+        let event = self.pending.pop_front()?;
+        Some(BackendEvent::Window(self.id, event))
+    }
+
+    /// Queues up whatever the surface has ready
+    fn collect(&mut self) {
+        if let Some(e) = self.surface.get_resize() {
+            let size = PhysicalSize::new(e.width, e.height);
+            self.pending.push_back(WindowEvent::Resized(size));
+        }
+        if let Some(e) = self.surface.get_pointer_move() {
+            self.pending.push_back(cursor_moved(&e));
+        }
+        let presses = [
+            (self.surface.get_pointer_down(), ElementState::Pressed),
+            (self.surface.get_pointer_up(), ElementState::Released),
+        ];
+        for (e, state) in presses {
+            if let Some(e) = e {
+                // Presses carry a position, which may not have been reported
+                // as a move
+                self.pending.push_back(cursor_moved(&e));
+                self.pending.push_back(WindowEvent::MouseInput {
+                    device_id: DeviceId::dummy(),
+                    state,
+                    button: MouseButton::Left,
+                });
+            }
+        }
+        let keys = [
+            (self.surface.get_key_down(), ElementState::Pressed),
+            (self.surface.get_key_up(), ElementState::Released),
+        ];
+        for (e, state) in keys {
+            let Some(e) = e else { continue };
+            if let Some(logical_key) = logical_key(&e) {
+                self.pending.push_back(WindowEvent::KeyboardInput {
+                    device_id: DeviceId::dummy(),
+                    event: KeyEvent {
+                        logical_key,
+                        text: e.text.filter(|t| !t.is_empty()),
+                        state,
+                        repeat: false,
+                    },
+                    is_synthetic: false,
+                });
+            }
+        }
+    }
+}
+
+fn cursor_moved(e: &surface::PointerEvent) -> WindowEvent {
+    WindowEvent::CursorMoved {
+        device_id: DeviceId::dummy(),
+        position: PhysicalPosition::new(e.x, e.y),
+    }
+}
+
+/// Maps a surface key to winit's logical key: named keys by their code, and
+/// everything else by the text it produces
+fn logical_key(e: &surface::KeyEvent) -> Option<Key> {
+    use surface::Key as K;
+    let named = match e.key? {
+        K::ShiftLeft | K::ShiftRight => NamedKey::Shift,
+        K::ControlLeft | K::ControlRight => NamedKey::Control,
+        K::AltLeft | K::AltRight => NamedKey::Alt,
+        K::MetaLeft | K::MetaRight => NamedKey::Super,
+        K::Enter => NamedKey::Enter,
+        K::Tab => NamedKey::Tab,
+        K::Space => NamedKey::Space,
+        K::ArrowDown => NamedKey::ArrowDown,
+        K::ArrowLeft => NamedKey::ArrowLeft,
+        K::ArrowRight => NamedKey::ArrowRight,
+        K::ArrowUp => NamedKey::ArrowUp,
+        K::End => NamedKey::End,
+        K::Home => NamedKey::Home,
+        K::PageDown => NamedKey::PageDown,
+        K::PageUp => NamedKey::PageUp,
+        K::Backspace => NamedKey::Backspace,
+        K::Delete => NamedKey::Delete,
+        K::Escape => NamedKey::Escape,
+        K::F1 => NamedKey::F1,
+        K::F2 => NamedKey::F2,
+        K::F3 => NamedKey::F3,
+        K::F4 => NamedKey::F4,
+        K::F5 => NamedKey::F5,
+        K::F6 => NamedKey::F6,
+        K::F7 => NamedKey::F7,
+        K::F8 => NamedKey::F8,
+        K::F9 => NamedKey::F9,
+        K::F10 => NamedKey::F10,
+        K::F11 => NamedKey::F11,
+        K::F12 => NamedKey::F12,
+        _ => return e.text.clone().filter(|t| !t.is_empty()).map(Key::Character),
+    };
+    Some(Key::Named(named))
+}
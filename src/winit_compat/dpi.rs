@@ -0,0 +1,25 @@
+//! Sizes and positions in physical pixels
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Default, Hash)]
+pub struct PhysicalSize<P> {
+    pub width: P,
+    pub height: P,
+}
+
+impl<P> PhysicalSize<P> {
+    pub const fn new(width: P, height: P) -> Self {
+        PhysicalSize { width, height }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Default, Hash)]
+pub struct PhysicalPosition<P> {
+    pub x: P,
+    pub y: P,
+}
+
+impl<P> PhysicalPosition<P> {
+    pub const fn new(x: P, y: P) -> Self {
+        PhysicalPosition { x, y }
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+/// An error reported by the backend, e.g. when it can't create a window
+#[derive(Debug, Clone)]
+pub struct OsError {
+    message: String,
+}
+
+impl OsError {
+    pub fn new(message: impl Into<String>) -> Self {
+        OsError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for OsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for OsError {}
+
+/// An error which stops the event loop from being created or from running
+#[derive(Debug, Clone)]
+pub enum EventLoopError {
+    Os(OsError),
+}
+
+impl From<OsError> for EventLoopError {
+    fn from(e: OsError) -> Self {
+        EventLoopError::Os(e)
+    }
+}
+
+impl fmt::Display for EventLoopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventLoopError::Os(e) => write!(f, "os error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EventLoopError {}
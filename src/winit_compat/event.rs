@@ -0,0 +1,177 @@
+//! Events which the event loop hands to the application
+
+use crate::time::Instant;
+
+use super::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    keyboard::Key,
+    window::WindowId,
+};
+
+/// Everything the event loop can report, as passed to the closure given to
+/// the deprecated [`EventLoop::run`](super::event_loop::EventLoop::run).
+/// Each variant matches an [`ApplicationHandler`](super::application::ApplicationHandler)
+/// method.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<T: 'static> {
+    NewEvents(StartCause),
+    WindowEvent {
+        window_id: WindowId,
+        event: WindowEvent,
+    },
+    DeviceEvent {
+        device_id: DeviceId,
+        event: DeviceEvent,
+    },
+    UserEvent(T),
+    Suspended,
+    Resumed,
+    AboutToWait,
+    LoopExiting,
+}
+
+/// Why the event loop woke up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartCause {
+    /// The deadline of a [`ControlFlow::WaitUntil`](super::event_loop::ControlFlow::WaitUntil)
+    /// has passed
+    ResumeTimeReached {
+        start: Instant,
+        requested_resume: Instant,
+    },
+    /// Events arrived, or a redraw was requested, before any deadline
+    WaitCancelled {
+        start: Instant,
+        requested_resume: Option<Instant>,
+    },
+    /// The control flow is [`ControlFlow::Poll`](super::event_loop::ControlFlow::Poll)
+    Poll,
+    /// The loop has just started
+    Init,
+}
+
+/// Identifies an input device.  Backends don't tell devices apart, so every
+/// event comes from the dummy device.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DeviceId(u64);
+
+impl DeviceId {
+    pub const fn dummy() -> Self {
+        DeviceId(0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowEvent {
+    Resized(PhysicalSize<u32>),
+    CloseRequested,
+    Destroyed,
+    Focused(bool),
+    KeyboardInput {
+        device_id: DeviceId,
+        event: KeyEvent,
+        /// Set for keys which were already held when the window gained focus
+        is_synthetic: bool,
+    },
+    CursorMoved {
+        device_id: DeviceId,
+        position: PhysicalPosition<f64>,
+    },
+    CursorLeft {
+        device_id: DeviceId,
+    },
+    MouseWheel {
+        device_id: DeviceId,
+        delta: MouseScrollDelta,
+        phase: TouchPhase,
+    },
+    MouseInput {
+        device_id: DeviceId,
+        state: ElementState,
+        button: MouseButton,
+    },
+    /// Two-finger pinch on a touchpad, positive when zooming in
+    PinchGesture {
+        device_id: DeviceId,
+        delta: f64,
+        phase: TouchPhase,
+    },
+    /// Two-finger rotation on a touchpad, in degrees counterclockwise
+    RotationGesture {
+        device_id: DeviceId,
+        delta: f32,
+        phase: TouchPhase,
+    },
+    Touch(Touch),
+    Occluded(bool),
+    RedrawRequested,
+}
+
+/// Raw input from a device, independent of any window
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceEvent {
+    Added,
+    Removed,
+    MouseMotion { delta: (f64, f64) },
+    MouseWheel { delta: MouseScrollDelta },
+    Button { button: u32, state: ElementState },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyEvent {
+    /// The key, as affected by the keyboard layout
+    pub logical_key: Key,
+    /// The text the key produces, if any
+    pub text: Option<String>,
+    pub state: ElementState,
+    /// Whether the key is being held down and this press is a repeat
+    pub repeat: bool,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum ElementState {
+    Pressed,
+    Released,
+}
+
+impl ElementState {
+    pub fn is_pressed(self) -> bool {
+        self == ElementState::Pressed
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+    Other(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseScrollDelta {
+    /// Lines and rows to scroll by, e.g. from a mouse wheel
+    LineDelta(f32, f32),
+    /// Pixels to scroll by, e.g. from a touchpad
+    PixelDelta(PhysicalPosition<f64>),
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+    Cancelled,
+}
+
+/// A finger on a touch screen.  Each finger's `id` stays the same from its
+/// `Started` event until its `Ended` or `Cancelled` one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Touch {
+    pub device_id: DeviceId,
+    pub phase: TouchPhase,
+    pub location: PhysicalPosition<f64>,
+    pub id: u64,
+}
@@ -0,0 +1,646 @@
+//! The loop which waits for events from the backend and hands them to the
+//! application
+
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::time::Instant;
+
+use super::{
+    application::ApplicationHandler,
+    backend::{Backend, BackendEvent},
+    error::{EventLoopError, OsError},
+    event::{DeviceEvent, DeviceId, Event, StartCause, WindowEvent},
+    window::{Window, WindowAttributes, WindowId},
+};
+
+/// What the loop does once it has handled every pending event.  Defaults to
+/// [`Wait`](Self::Wait).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ControlFlow {
+    /// Start the next iteration straight away
+    Poll,
+    /// Sleep until another event arrives
+    #[default]
+    Wait,
+    /// Sleep until another event arrives or the given time is reached
+    WaitUntil(Instant),
+}
+
+impl ControlFlow {
+    /// Waits for at most the given time from now
+    pub fn wait_duration(timeout: Duration) -> Self {
+        ControlFlow::WaitUntil(Instant::now() + timeout)
+    }
+
+    fn deadline(self) -> Option<Instant> {
+        match self {
+            ControlFlow::WaitUntil(deadline) => Some(deadline),
+            _ => None,
+        }
+    }
+}
+
+pub struct EventLoop<T: 'static> {
+    target: ActiveEventLoop,
+    _marker: PhantomData<T>,
+}
+
+impl EventLoop<()> {
+    /// Creates a loop on the platform's backend
+    #[cfg(target_arch="wasm32")]
+    pub fn new() -> Result<EventLoop<()>, EventLoopError> {
+        Ok(EventLoop::with_backend(super::backend::platform()))
+    }
+}
+
+impl<T: 'static> EventLoop<T> {
+    /// Creates a loop on the given backend.  This isn't part of winit.
+    pub fn with_backend(backend: Box<dyn Backend>) -> Self {
+        EventLoop {
+            target: ActiveEventLoop {
+                backend: RefCell::new(backend),
+                control_flow: Cell::new(ControlFlow::default()),
+                exiting: Cell::new(false),
+                next_window_id: Cell::new(0),
+                redraws: Rc::new(RefCell::new(vec![])),
+            },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Runs the loop, passing each event to a closure
+    #[deprecated = "use `EventLoop::run_app` instead"]
+    pub fn run<F>(self, handler: F) -> Result<(), EventLoopError>
+    where
+        F: FnMut(Event<T>, &ActiveEventLoop),
+    {
+        self.run_app(&mut EventHandler {
+            handler,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Runs the loop until the application calls [`ActiveEventLoop::exit`].
+    /// Each iteration hands over the backend's pending events, then sends
+    /// `RedrawRequested` to the windows which asked for it, then calls
+    /// `about_to_wait` and waits as the control flow says.
+    pub fn run_app<A: ApplicationHandler<T>>(self, app: &mut A) -> Result<(), EventLoopError> {
+        let target = &self.target;
+        let mut suspended = false;
+        app.new_events(target, StartCause::Init);
+        app.resumed(target);
+        loop {
+            // The backend mustn't stay borrowed while the app runs, in case
+            // it creates a window
+            while let Some(event) = target.next_event() {
+                match event {
+                    BackendEvent::Window(id, event) => app.window_event(target, id, event),
+                    BackendEvent::Device(event) => {
+                        app.device_event(target, DeviceId::dummy(), event)
+                    }
+                    BackendEvent::Suspended if !suspended => {
+                        suspended = true;
+                        app.suspended(target);
+                    }
+                    BackendEvent::Resumed if suspended => {
+                        suspended = false;
+                        app.resumed(target);
+                    }
+                    BackendEvent::Suspended | BackendEvent::Resumed => (),
+                }
+            }
+            // Redraws requested while drawing wait for the next iteration
+            let redraws = std::mem::take(&mut *target.redraws.borrow_mut());
+            for id in redraws {
+                app.window_event(target, id, WindowEvent::RedrawRequested);
+            }
+            app.about_to_wait(target);
+            if target.exiting() {
+                break;
+            }
+
+            let start = Instant::now();
+            let cause = match target.control_flow() {
+                ControlFlow::Poll => StartCause::Poll,
+                ControlFlow::Wait | ControlFlow::WaitUntil(_) if target.redraw_pending() => {
+                    StartCause::WaitCancelled {
+                        start,
+                        requested_resume: target.control_flow().deadline(),
+                    }
+                }
+                ControlFlow::Wait => {
+                    target.backend.borrow_mut().wait(None);
+                    StartCause::WaitCancelled {
+                        start,
+                        requested_resume: None,
+                    }
+                }
+                ControlFlow::WaitUntil(deadline) => {
+                    target.backend.borrow_mut().wait(Some(deadline));
+                    if Instant::now() >= deadline {
+                        StartCause::ResumeTimeReached {
+                            start,
+                            requested_resume: deadline,
+                        }
+                    } else {
+                        StartCause::WaitCancelled {
+                            start,
+                            requested_resume: Some(deadline),
+                        }
+                    }
+                }
+            };
+            app.new_events(target, cause);
+        }
+        app.exiting(target);
+        Ok(())
+    }
+
+    pub fn set_control_flow(&self, control_flow: ControlFlow) {
+        self.target.set_control_flow(control_flow)
+    }
+
+    #[deprecated = "use `ActiveEventLoop::create_window` instead"]
+    pub fn create_window(&self, attributes: WindowAttributes) -> Result<Window, OsError> {
+        self.target.create_window(attributes)
+    }
+}
+
+/// The running loop, as handed to the application
+pub struct ActiveEventLoop {
+    backend: RefCell<Box<dyn Backend>>,
+    control_flow: Cell<ControlFlow>,
+    exiting: Cell<bool>,
+    next_window_id: Cell<u64>,
+    /// Windows waiting for a `RedrawRequested`, shared with the windows
+    redraws: Rc<RefCell<Vec<WindowId>>>,
+}
+
+#[deprecated = "use `ActiveEventLoop` instead"]
+pub type EventLoopWindowTarget = ActiveEventLoop;
+
+impl ActiveEventLoop {
+    pub fn create_window(&self, attributes: WindowAttributes) -> Result<Window, OsError> {
+        let id = WindowId::from(self.next_window_id.get());
+        let platform = self.backend.borrow_mut().create_window(id, &attributes)?;
+        self.next_window_id.set(u64::from(id) + 1);
+        Ok(Window::new(id, platform, Rc::clone(&self.redraws)))
+    }
+
+    pub fn set_control_flow(&self, control_flow: ControlFlow) {
+        self.control_flow.set(control_flow)
+    }
+
+    pub fn control_flow(&self) -> ControlFlow {
+        self.control_flow.get()
+    }
+
+    /// Ends the loop after the current iteration
+    pub fn exit(&self) {
+        self.exiting.set(true)
+    }
+
+    /// Returns true if `exit` has been called
+    pub fn exiting(&self) -> bool {
+        self.exiting.get()
+    }
+
+    fn next_event(&self) -> Option<BackendEvent> {
+        self.backend.borrow_mut().next_event()
+    }
+
+    fn redraw_pending(&self) -> bool {
+        !self.redraws.borrow().is_empty()
+    }
+}
+
+/// Adapts the closure given to the deprecated `EventLoop::run`
+struct EventHandler<F, T> {
+    handler: F,
+    _marker: PhantomData<T>,
+}
+
+impl<F, T> ApplicationHandler<T> for EventHandler<F, T>
+where
+    F: FnMut(Event<T>, &ActiveEventLoop),
+    T: 'static,
+{
+    fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
+        (self.handler)(Event::NewEvents(cause), event_loop);
+    }
+
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        (self.handler)(Event::Resumed, event_loop);
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: T) {
+        (self.handler)(Event::UserEvent(event), event_loop);
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        (self.handler)(Event::WindowEvent { window_id, event }, event_loop);
+    }
+
+    fn device_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        device_id: DeviceId,
+        event: DeviceEvent,
+    ) {
+        (self.handler)(Event::DeviceEvent { device_id, event }, event_loop);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        (self.handler)(Event::AboutToWait, event_loop);
+    }
+
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        (self.handler)(Event::Suspended, event_loop);
+    }
+
+    fn exiting(&mut self, event_loop: &ActiveEventLoop) {
+        (self.handler)(Event::LoopExiting, event_loop);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::winit_compat::{backend::mock::Mock, dpi::PhysicalSize};
+
+    /// Records everything the loop hands it, as the closure-style events, and
+    /// lets each test act on the loop after every one
+    struct Recorder<F> {
+        events: Vec<Event<()>>,
+        window: Option<Window>,
+        act: F,
+    }
+
+    impl<F> Recorder<F>
+    where
+        F: FnMut(&ActiveEventLoop, &Event<()>, &mut Option<Window>),
+    {
+        fn new(act: F) -> Self {
+            Recorder {
+                events: vec![],
+                window: None,
+                act,
+            }
+        }
+
+        fn record(&mut self, event_loop: &ActiveEventLoop, event: Event<()>) {
+            (self.act)(event_loop, &event, &mut self.window);
+            self.events.push(event);
+        }
+
+        /// Returns how many times the loop reached `AboutToWait`
+        fn iterations(&self) -> usize {
+            self.events
+                .iter()
+                .filter(|e| matches!(e, Event::AboutToWait))
+                .count()
+        }
+    }
+
+    impl<F> ApplicationHandler for Recorder<F>
+    where
+        F: FnMut(&ActiveEventLoop, &Event<()>, &mut Option<Window>),
+    {
+        fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
+            self.record(event_loop, Event::NewEvents(cause));
+        }
+
+        fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+            self.record(event_loop, Event::Resumed);
+        }
+
+        fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, e: WindowEvent) {
+            self.record(
+                event_loop,
+                Event::WindowEvent {
+                    window_id: id,
+                    event: e,
+                },
+            );
+        }
+
+        fn device_event(&mut self, event_loop: &ActiveEventLoop, id: DeviceId, e: DeviceEvent) {
+            self.record(
+                event_loop,
+                Event::DeviceEvent {
+                    device_id: id,
+                    event: e,
+                },
+            );
+        }
+
+        fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+            self.record(event_loop, Event::AboutToWait);
+        }
+
+        fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+            self.record(event_loop, Event::Suspended);
+        }
+
+        fn exiting(&mut self, event_loop: &ActiveEventLoop) {
+            self.record(event_loop, Event::LoopExiting);
+        }
+    }
+
+    fn window_event(id: u64, event: WindowEvent) -> BackendEvent {
+        BackendEvent::Window(WindowId::from(id), event)
+    }
+
+    fn exit_on_about_to_wait(event_loop: &ActiveEventLoop, event: &Event<()>) {
+        if matches!(event, Event::AboutToWait) {
+            event_loop.exit();
+        }
+    }
+
+    #[test]
+    fn dispatches_pending_events_in_order() {
+        let mock = Mock::new();
+        mock.send(window_event(0, WindowEvent::Focused(true)));
+        mock.send(BackendEvent::Device(DeviceEvent::Added));
+        mock.send(window_event(0, WindowEvent::CloseRequested));
+        let mut app =
+            Recorder::new(|event_loop, event, _| exit_on_about_to_wait(event_loop, event));
+        EventLoop::with_backend(Box::new(mock.clone()))
+            .run_app(&mut app)
+            .unwrap();
+
+        let window_id = WindowId::from(0);
+        assert_eq!(
+            app.events,
+            [
+                Event::NewEvents(StartCause::Init),
+                Event::Resumed,
+                Event::WindowEvent {
+                    window_id,
+                    event: WindowEvent::Focused(true),
+                },
+                Event::DeviceEvent {
+                    device_id: DeviceId::dummy(),
+                    event: DeviceEvent::Added,
+                },
+                Event::WindowEvent {
+                    window_id,
+                    event: WindowEvent::CloseRequested,
+                },
+                Event::AboutToWait,
+                Event::LoopExiting,
+            ]
+        );
+        assert!(mock.waits().is_empty());
+    }
+
+    #[test]
+    fn creates_windows_with_their_own_ids() {
+        let mock = Mock::new();
+        let mut app = Recorder::new(|event_loop, event, window| {
+            if matches!(event, Event::Resumed) {
+                let attributes = Window::default_attributes().with_title("first");
+                let first = event_loop.create_window(attributes).unwrap();
+                let second = event_loop.create_window(Default::default()).unwrap();
+                assert_ne!(first.id(), second.id());
+                assert_eq!(first.inner_size().width, 800);
+                *window = Some(first);
+            }
+            exit_on_about_to_wait(event_loop, event);
+        });
+        EventLoop::with_backend(Box::new(mock.clone()))
+            .run_app(&mut app)
+            .unwrap();
+
+        let windows = mock.windows();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0], (WindowId::from(0), "first".to_string()));
+        assert_eq!(windows[1], (WindowId::from(1), "winit window".to_string()));
+    }
+
+    #[test]
+    fn reports_window_creation_errors() {
+        let mock = Mock::new();
+        mock.fail_windows();
+        let mut app = Recorder::new(|event_loop, event, _| {
+            if matches!(event, Event::Resumed) {
+                assert!(event_loop.create_window(Default::default()).is_err());
+            }
+            exit_on_about_to_wait(event_loop, event);
+        });
+        EventLoop::with_backend(Box::new(mock.clone()))
+            .run_app(&mut app)
+            .unwrap();
+        assert!(mock.windows().is_empty());
+    }
+
+    #[test]
+    fn redraws_once_after_input() {
+        let mock = Mock::new();
+        mock.send(window_event(
+            0,
+            WindowEvent::Resized(PhysicalSize::new(640, 480)),
+        ));
+        let mut app = Recorder::new(|event_loop, event, window| {
+            match event {
+                Event::Resumed => {
+                    *window = Some(event_loop.create_window(Default::default()).unwrap())
+                }
+                Event::WindowEvent {
+                    event: WindowEvent::Resized(_),
+                    ..
+                } => {
+                    let window = window.as_ref().unwrap();
+                    window.request_redraw();
+                    window.request_redraw();
+                }
+                _ => (),
+            }
+            exit_on_about_to_wait(event_loop, event);
+        });
+        EventLoop::with_backend(Box::new(mock))
+            .run_app(&mut app)
+            .unwrap();
+
+        let after_init = &app.events[2..];
+        assert!(matches!(
+            after_init[0],
+            Event::WindowEvent {
+                event: WindowEvent::Resized(_),
+                ..
+            }
+        ));
+        assert_eq!(
+            after_init[1],
+            Event::WindowEvent {
+                window_id: WindowId::from(0),
+                event: WindowEvent::RedrawRequested,
+            }
+        );
+        assert_eq!(after_init[2], Event::AboutToWait);
+    }
+
+    #[test]
+    fn pending_redraw_skips_the_wait() {
+        let mock = Mock::new();
+        let mut app = Recorder::new(|event_loop, event, window| match event {
+            Event::Resumed => *window = Some(event_loop.create_window(Default::default()).unwrap()),
+            Event::AboutToWait if window.is_some() => {
+                // Asked for while waiting, so it's drawn next iteration
+                window.take().unwrap().request_redraw();
+            }
+            Event::AboutToWait => event_loop.exit(),
+            _ => (),
+        });
+        EventLoop::with_backend(Box::new(mock.clone()))
+            .run_app(&mut app)
+            .unwrap();
+
+        assert!(mock.waits().is_empty());
+        assert_eq!(app.iterations(), 2);
+        assert!(app.events.contains(&Event::WindowEvent {
+            window_id: WindowId::from(0),
+            event: WindowEvent::RedrawRequested,
+        }));
+    }
+
+    #[test]
+    fn waits_for_events() {
+        let mock = Mock::new();
+        mock.send_after_wait(vec![window_event(0, WindowEvent::CloseRequested)]);
+        let mut app = Recorder::new(|event_loop, event, _| {
+            if let Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } = event
+            {
+                event_loop.exit();
+            }
+        });
+        EventLoop::with_backend(Box::new(mock.clone()))
+            .run_app(&mut app)
+            .unwrap();
+
+        assert_eq!(mock.waits(), [None]);
+        assert!(app.events.iter().any(|e| matches!(
+            e,
+            Event::NewEvents(StartCause::WaitCancelled {
+                requested_resume: None,
+                ..
+            })
+        )));
+    }
+
+    #[test]
+    fn waits_until_the_deadline() {
+        let mock = Mock::new();
+        let deadline = Instant::now();
+        let mut app = Recorder::new(|event_loop, event, _| {
+            if matches!(event, Event::AboutToWait) {
+                if event_loop.control_flow() == ControlFlow::Wait {
+                    event_loop.set_control_flow(ControlFlow::WaitUntil(deadline));
+                } else {
+                    event_loop.exit();
+                }
+            }
+        });
+        EventLoop::with_backend(Box::new(mock.clone()))
+            .run_app(&mut app)
+            .unwrap();
+
+        assert_eq!(mock.waits(), [Some(deadline)]);
+        assert!(app.events.iter().any(|e| matches!(
+            e,
+            Event::NewEvents(StartCause::ResumeTimeReached { requested_resume, .. })
+                if *requested_resume == deadline
+        )));
+    }
+
+    #[test]
+    fn polls_without_waiting() {
+        let mock = Mock::new();
+        let mut iterations = 0;
+        let mut app = Recorder::new(|event_loop, event, _| match event {
+            Event::NewEvents(StartCause::Init) => event_loop.set_control_flow(ControlFlow::Poll),
+            Event::AboutToWait => {
+                iterations += 1;
+                if iterations == 3 {
+                    event_loop.exit();
+                }
+            }
+            _ => (),
+        });
+        EventLoop::with_backend(Box::new(mock.clone()))
+            .run_app(&mut app)
+            .unwrap();
+
+        assert!(mock.waits().is_empty());
+        assert_eq!(app.iterations(), 3);
+        let polls = app
+            .events
+            .iter()
+            .filter(|e| **e == Event::NewEvents(StartCause::Poll))
+            .count();
+        assert_eq!(polls, 2);
+    }
+
+    #[test]
+    fn suspends_and_resumes_once_each() {
+        let mock = Mock::new();
+        mock.send(BackendEvent::Resumed);
+        mock.send(BackendEvent::Suspended);
+        mock.send(BackendEvent::Suspended);
+        mock.send(BackendEvent::Resumed);
+        mock.send(BackendEvent::Resumed);
+        let mut app =
+            Recorder::new(|event_loop, event, _| exit_on_about_to_wait(event_loop, event));
+        EventLoop::with_backend(Box::new(mock))
+            .run_app(&mut app)
+            .unwrap();
+
+        assert_eq!(
+            app.events,
+            [
+                Event::NewEvents(StartCause::Init),
+                Event::Resumed,
+                Event::Suspended,
+                Event::Resumed,
+                Event::AboutToWait,
+                Event::LoopExiting,
+            ]
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn run_passes_events_to_a_closure() {
+        let mock = Mock::new();
+        mock.send(window_event(0, WindowEvent::Focused(false)));
+        let mut events = vec![];
+        EventLoop::<()>::with_backend(Box::new(mock))
+            .run(|event, event_loop| {
+                exit_on_about_to_wait(event_loop, &event);
+                events.push(event);
+            })
+            .unwrap();
+
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0], Event::NewEvents(StartCause::Init));
+        assert_eq!(
+            events[2],
+            Event::WindowEvent {
+                window_id: WindowId::from(0),
+                event: WindowEvent::Focused(false),
+            }
+        );
+        assert_eq!(events[4], Event::LoopExiting);
+    }
+}
@@ -0,0 +1,66 @@
+//! Keys, as named by the keyboard layout
+
+/// Keys which don't produce text, with winit's names so key bindings read
+/// the same on every target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NamedKey {
+    // Modifiers and locks
+    Alt,
+    AltGraph,
+    CapsLock,
+    Control,
+    Fn,
+    FnLock,
+    NumLock,
+    ScrollLock,
+    Shift,
+    Symbol,
+    SymbolLock,
+    Meta,
+    Hyper,
+    /// The Windows logo key, or the Apple Command key
+    Super,
+
+    // Whitespace
+    Enter,
+    Tab,
+    Space,
+
+    // Navigation
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+    End,
+    Home,
+    PageDown,
+    PageUp,
+
+    // Editing
+    Backspace,
+    Delete,
+    Escape,
+
+    // Function keys
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    Named(NamedKey),
+    /// The text the key types, taking the layout and modifiers into account
+    Character(String),
+    /// A dead key, with the accent it adds if known
+    Dead(Option<char>),
+}
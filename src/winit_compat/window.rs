@@ -0,0 +1,106 @@
+//! Windows, which the backend creates for the event loop
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wgpu::rwh::{DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle};
+
+use super::{backend::PlatformWindow, dpi::PhysicalSize};
+
+/// Identifies a window in the events the loop reports
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WindowId(u64);
+
+impl WindowId {
+    pub const fn dummy() -> Self {
+        WindowId(0)
+    }
+}
+
+impl From<u64> for WindowId {
+    fn from(id: u64) -> Self {
+        WindowId(id)
+    }
+}
+
+impl From<WindowId> for u64 {
+    fn from(id: WindowId) -> Self {
+        id.0
+    }
+}
+
+/// Settings for a new window, passed to
+/// [`ActiveEventLoop::create_window`](super::event_loop::ActiveEventLoop::create_window)
+#[derive(Debug, Clone)]
+pub struct WindowAttributes {
+    pub title: String,
+}
+
+impl Default for WindowAttributes {
+    fn default() -> Self {
+        WindowAttributes {
+            title: "winit window".to_string(),
+        }
+    }
+}
+
+impl WindowAttributes {
+    pub fn with_title<T: Into<String>>(mut self, title: T) -> Self {
+        self.title = title.into();
+        self
+    }
+}
+
+pub struct Window {
+    id: WindowId,
+    platform: Box<dyn PlatformWindow>,
+    /// Windows waiting for a `RedrawRequested`, shared with the event loop
+    redraws: Rc<RefCell<Vec<WindowId>>>,
+}
+
+impl Window {
+    pub(super) fn new(
+        id: WindowId,
+        platform: Box<dyn PlatformWindow>,
+        redraws: Rc<RefCell<Vec<WindowId>>>,
+    ) -> Self {
+        Window {
+            id,
+            platform,
+            redraws,
+        }
+    }
+
+    pub fn default_attributes() -> WindowAttributes {
+        WindowAttributes::default()
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    pub fn inner_size(&self) -> PhysicalSize<u32> {
+        self.platform.inner_size()
+    }
+
+    /// Asks for a `RedrawRequested` event, which the loop sends once it has
+    /// handled the pending input.  Asking again before then has no effect.
+    pub fn request_redraw(&self) {
+        let mut redraws = self.redraws.borrow_mut();
+        if !redraws.contains(&self.id) {
+            redraws.push(self.id);
+        }
+    }
+}
+
+impl HasWindowHandle for Window {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        self.platform.window_handle()
+    }
+}
+
+impl HasDisplayHandle for Window {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        self.platform.display_handle()
+    }
+}